@@ -4,14 +4,21 @@
  * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{collections::BTreeSet, fmt::Display};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashSet},
+	fmt::Display,
+};
 
 use crate::util::GradleSpecifier;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, skip_serializing_none, OneOrMany};
+use serde_with::{hex::Hex, serde_as, skip_serializing_none, OneOrMany};
+use sha1::{Digest, Sha1};
+use sha2::{Sha256, Sha512};
+use thiserror::Error;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum OsName {
 	Linux,
@@ -20,29 +27,181 @@ pub enum OsName {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ComponentDependency {
 	pub id: String,
 	#[serde(skip_serializing_if = "Option::is_none", default)]
 	pub version: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl Display for ComponentDependency {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.version {
+			Some(version) => write!(f, "{} (={version})", self.id),
+			None => write!(f, "{} (any)", self.id),
+		}
+	}
+}
+
+/// The relations of a component to other components. This is flattened into [Component] and
+/// [crate::index::IndexEntry], so the fields appear at the top level in JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Dependencies {
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub requires: Vec<ComponentDependency>,
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub conflicts: Vec<ComponentDependency>,
+	/// Other components this one can stand in for when resolving `requires`, e.g. a LegacyFabric
+	/// intermediary providing `net.fabricmc.intermediary`.
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub provides: Vec<ComponentDependency>,
+	/// Components this one makes use of if they are selected, but doesn't require. As part of
+	/// [Dependencies] these are in the index too, so a resolver can offer them without loading
+	/// the component.
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub optional: Vec<ComponentDependency>,
+}
+
+/// A digest of a file. The digest is kept as bytes, and (de)serialized as lowercase hex.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Hash {
-	SHA256(String),
-	SHA1(String),
+	SHA256(
+		#[cfg_attr(feature = "schemars", schemars(with = "String"))]
+		#[serde_as(as = "Hex")]
+		[u8; 32],
+	),
+	SHA1(
+		#[cfg_attr(feature = "schemars", schemars(with = "String"))]
+		#[serde_as(as = "Hex")]
+		[u8; 20],
+	),
+	SHA512(
+		#[cfg_attr(feature = "schemars", schemars(with = "String"))]
+		#[serde_as(as = "Hex")]
+		[u8; 64],
+	),
 }
 
-impl Display for Hash {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+	Sha1,
+	Sha256,
+	Sha512,
+}
+
+impl Display for HashAlgorithm {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			HashAlgorithm::Sha1 => "SHA1",
+			HashAlgorithm::Sha256 => "SHA256",
+			HashAlgorithm::Sha512 => "SHA512",
+		})
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum HashParseError {
+	#[error("\"{0}\" is not a hex string!")]
+	NotHex(String),
+	#[error("\"{0}\" does not have the length of a known digest!")]
+	UnknownLength(String),
+	#[error("\"{hex}\" is not {expected} bytes long!")]
+	WrongLength { hex: String, expected: usize },
+}
+
+fn decode_hex<const N: usize>(hex: &str) -> Result<[u8; N], HashParseError> {
+	if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+		return Err(HashParseError::NotHex(hex.to_owned()));
+	}
+	if hex.len() != N * 2 {
+		return Err(HashParseError::WrongLength {
+			hex: hex.to_owned(),
+			expected: N,
+		});
+	}
+	let mut bytes = [0; N];
+	for (byte, digits) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+		// both digits were checked above
+		*byte = u8::from_str_radix(std::str::from_utf8(digits).unwrap(), 16).unwrap();
+	}
+	Ok(bytes)
+}
+
+impl Hash {
+	/// Parses a hex SHA-1 digest, in either case.
+	pub fn parse_sha1(hex: &str) -> Result<Self, HashParseError> {
+		decode_hex(hex).map(Hash::SHA1)
+	}
+
+	/// Parses a hex SHA-256 digest, in either case.
+	pub fn parse_sha256(hex: &str) -> Result<Self, HashParseError> {
+		decode_hex(hex).map(Hash::SHA256)
+	}
+
+	/// Parses a hex SHA-512 digest, in either case.
+	pub fn parse_sha512(hex: &str) -> Result<Self, HashParseError> {
+		decode_hex(hex).map(Hash::SHA512)
+	}
+
+	/// Parses a hex digest, inferring the algorithm from its length.
+	pub fn from_hex_guess(hex: &str) -> Result<Self, HashParseError> {
+		match hex.len() {
+			40 => Self::parse_sha1(hex),
+			64 => Self::parse_sha256(hex),
+			128 => Self::parse_sha512(hex),
+			_ if !hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+				Err(HashParseError::NotHex(hex.to_owned()))
+			}
+			_ => Err(HashParseError::UnknownLength(hex.to_owned())),
+		}
+	}
+
+	pub fn algorithm(&self) -> HashAlgorithm {
+		match self {
+			Hash::SHA1(_) => HashAlgorithm::Sha1,
+			Hash::SHA256(_) => HashAlgorithm::Sha256,
+			Hash::SHA512(_) => HashAlgorithm::Sha512,
+		}
+	}
+
+	/// Returns the digest as lowercase hex. This allocates, as the digest is stored as bytes.
+	pub fn hex(&self) -> String {
+		self.bytes()
+			.iter()
+			.map(|byte| format!("{byte:02x}"))
+			.collect()
+	}
+
+	/// Whether `data` has this digest.
+	pub fn verify(&self, data: &[u8]) -> bool {
+		match self {
+			Hash::SHA1(digest) => Sha1::digest(data).as_slice() == digest,
+			Hash::SHA256(digest) => Sha256::digest(data).as_slice() == digest,
+			Hash::SHA512(digest) => Sha512::digest(data).as_slice() == digest,
+		}
+	}
+
+	fn bytes(&self) -> &[u8] {
 		match self {
-			Hash::SHA1(hash) => write!(f, "SHA1 hash {hash}"),
-			Hash::SHA256(hash) => write!(f, "SHA256 hash {hash}"),
+			Hash::SHA1(bytes) => bytes,
+			Hash::SHA256(bytes) => bytes,
+			Hash::SHA512(bytes) => bytes,
 		}
 	}
 }
 
+impl Display for Hash {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} hash {}", self.algorithm(), self.hex())
+	}
+}
+
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Download {
 	pub name: GradleSpecifier,
 	pub url: String,
@@ -53,36 +212,90 @@ pub struct Download {
 
 /// A trait of a component or instance.
 ///
+/// New variants are added as processors learn to detect more capabilities, so launchers should
+/// expect traits they don't know. The PascalCase names written by older generators are still
+/// accepted.
+///
 /// The [Ord] and [PartialOrd] impls are for use with [BTreeSet].
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
 pub enum Trait {
 	/// This component needs -XstartOnFirstThread on macOS.
+	#[serde(alias = "MacStartOnFirstThread")]
 	MacStartOnFirstThread,
+	#[serde(alias = "SupportsCustomResolution")]
 	SupportsCustomResolution,
+	#[serde(alias = "SupportsQuickPlayServerLegacy")]
 	SupportsQuickPlayServerLegacy,
+	#[serde(alias = "SupportsQuickPlayServer")]
 	SupportsQuickPlayServer,
+	#[serde(alias = "SupportsQuickPlayWorld")]
 	SupportsQuickPlayWorld,
+	/// The game reads assets by their path instead of by hash, so the launcher has to lay out a
+	/// virtual asset directory (`${instance.virtual_assets_dir}`) for it.
+	LegacyAssets,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum Arch {
 	X86,
 	X86_64,
+	Arm32,
 	Arm64,
+	Riscv64,
+}
+
+impl Arch {
+	/// Whether a host of this architecture can load natives built for `native_arch`.
+	///
+	/// x86_64 hosts can run x86 natives; emulation layers such as Rosetta are not considered.
+	pub fn can_run(self, native_arch: Arch) -> bool {
+		self == native_arch || matches!((self, native_arch), (Arch::X86_64, Arch::X86))
+	}
 }
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Platform {
 	#[serde(skip_serializing_if = "Vec::is_empty", default)]
 	#[serde_as(as = "OneOrMany<_>")]
 	pub os: Vec<OsName>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub arch: Option<Arch>,
+	/// A regex the OS version has to match, as found in Mojang's rules. It is usually specific to
+	/// one OS.
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub os_version: Option<String>,
+}
+
+impl Platform {
+	/// Whether this platform includes `os` and `arch`. An empty `os` list matches any OS, and a
+	/// missing architecture on either side matches any architecture. `os_version` is not checked,
+	/// as that needs the version of the running OS.
+	pub fn matches(&self, os: OsName, arch: Option<Arch>) -> bool {
+		(self.os.is_empty() || self.os.contains(&os))
+			&& match (self.arch, arch) {
+				(Some(platform_arch), Some(arch)) => platform_arch == arch,
+				_ => true,
+			}
+	}
+}
+
+/// A [Trait] that only applies on some platforms.
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PlatformTrait {
+	#[serde(rename = "trait")]
+	pub kind: Trait,
+	pub platform: Platform,
 }
 
 #[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Native {
 	pub name: GradleSpecifier,
 	pub platform: Platform,
@@ -91,6 +304,7 @@ pub struct Native {
 }
 
 #[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ConditionalClasspathEntry {
 	All(GradleSpecifier),
@@ -100,7 +314,21 @@ pub enum ConditionalClasspathEntry {
 	},
 }
 
+impl ConditionalClasspathEntry {
+	/// Whether this entry is on the classpath when launching on `os` and `arch`, following the
+	/// rules of [Platform::matches].
+	pub fn applies_to(&self, os: OsName, arch: Option<Arch>) -> bool {
+		match self {
+			ConditionalClasspathEntry::All(_) => true,
+			ConditionalClasspathEntry::PlatformSpecific { platform, .. } => {
+				platform.matches(os, arch)
+			}
+		}
+	}
+}
+
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Assets {
 	pub id: String,
 	pub url: String,
@@ -109,7 +337,76 @@ pub struct Assets {
 	pub total_size: u32, // TODO: is this really necessary?
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// A log4j2 configuration file for the game.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Logging {
+	pub id: String,
+	pub url: String,
+	pub sha1: String,
+	pub size: u32,
+	/// The JVM argument selecting the configuration, with the downloaded file in
+	/// `${launch.logging_config}`.
+	pub argument: String,
+}
+
+/// A Java runtime built by Mojang for one platform.
+///
+/// `url` points to Mojang's manifest listing the individual files of the runtime.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct JavaRuntime {
+	pub platform: Platform,
+	/// The Java version of the runtime, e.g. `17.0.8`.
+	pub version: String,
+	pub url: String,
+	pub sha1: String,
+	pub size: u32,
+}
+
+/// Steps the launcher runs once, before the first launch, to set up a component, as done by the
+/// installers of modern Forge versions.
+///
+/// Arguments and outputs of the processors may contain `{KEY}`, which stands for the entry `KEY`
+/// of [Installer::data], and `[coordinate]`, which stands for the path of that artifact in the
+/// libraries directory. Data values are themselves either `[coordinate]`, a literal `'string'`
+/// or a `/path` inside the installer jar. The launcher provides `MINECRAFT_JAR`,
+/// `MINECRAFT_VERSION`, `SIDE` (always `client`), `ROOT`, `INSTALLER` and `LIBRARY_DIR` itself.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Installer {
+	/// The installer jar, which has to be one of the downloads of the component.
+	pub installer: GradleSpecifier,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+	pub data: BTreeMap<String, String>,
+	/// The processors, in the order they have to run in.
+	pub processors: Vec<Processor>,
+	/// Libraries without a download that are extracted from `maven/<path>` in the installer jar.
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub bundled: Vec<GradleSpecifier>,
+	/// Libraries without a download that the processors write, such as the patched game jar.
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub generated: Vec<GradleSpecifier>,
+}
+
+/// A step of an [Installer]: running the main class of `jar` with `classpath` and `args`.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Processor {
+	pub jar: GradleSpecifier,
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub classpath: Vec<GradleSpecifier>,
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub args: Vec<String>,
+	/// The files this processor writes, mapped to their SHA-1. If every one of them exists with
+	/// the right hash, the processor doesn't have to run again.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+	pub outputs: BTreeMap<String, String>,
+}
+
+/// The [Ord] and [PartialOrd] impls are for use with [BTreeSet].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ConditionFeature {
 	Demo,
@@ -122,6 +419,7 @@ pub enum ConditionFeature {
 
 // TODO: this feels a bit hacky?
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum MinecraftArgument {
 	Always(String),
@@ -129,31 +427,591 @@ pub enum MinecraftArgument {
 		value: String,
 		feature: ConditionFeature,
 	},
+	PlatformSpecific {
+		value: String,
+		platform: Platform,
+	},
+}
+
+/// The version of the component format written by this crate.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// An artifact that is downloaded more than once, with different sizes or hashes.
+#[derive(Debug, Clone)]
+pub struct DownloadConflict {
+	pub name: GradleSpecifier,
+	/// The size and hash of every download of the artifact, in order.
+	pub variants: Vec<(u32, Hash)>,
+}
+
+impl Display for DownloadConflict {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} is downloaded as", self.name)?;
+		for (i, (size, hash)) in self.variants.iter().enumerate() {
+			let separator = if i == 0 { "" } else { "," };
+			write!(f, "{separator} {size} bytes with {hash}")?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum ValidationError {
+	#[error("Unsupported format version {0}")]
+	UnsupportedFormatVersion(u32),
+	#[error("{0} is downloaded more than once")]
+	DuplicateDownload(GradleSpecifier),
+	#[error("Downloads conflict: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+	ConflictingDownloads(Vec<DownloadConflict>),
+	#[error("Classpath entry {0} has no download")]
+	MissingClasspathDownload(GradleSpecifier),
+	#[error("Native {0} has no download")]
+	MissingNativeDownload(GradleSpecifier),
+	#[error("Jarmod {0} has no download")]
+	MissingJarmodDownload(GradleSpecifier),
+	#[error("Game jar {0} has no download")]
+	MissingGameJarDownload(GradleSpecifier),
+	#[error("Installer {0} has no download")]
+	MissingInstallerDownload(GradleSpecifier),
+	#[error("Processor library {0} has no download")]
+	MissingProcessorDownload(GradleSpecifier),
+}
+
+#[derive(Error, Debug)]
+pub enum UpgradeError {
+	#[error("Missing format_version")]
+	MissingFormatVersion,
+	#[error("Unsupported format version {0}")]
+	UnsupportedFormatVersion(u64),
+	#[error(transparent)]
+	Invalid(#[from] serde_json::Error),
+}
+
+/// Reads a component in any format version this crate knows, migrating it to [FORMAT_VERSION]
+/// first. Components written by other versions of the generator should be read through this
+/// instead of deserializing [Component] directly.
+pub fn upgrade(value: serde_json::Value) -> Result<Component, UpgradeError> {
+	let format_version = value
+		.get("format_version")
+		.and_then(serde_json::Value::as_u64)
+		.ok_or(UpgradeError::MissingFormatVersion)?;
+	// a migration from version n rewrites `value` into version n + 1 and sets its
+	// format_version, so that older components go through every later migration as well
+	match format_version {
+		1 => {}
+		_ => return Err(UpgradeError::UnsupportedFormatVersion(format_version)),
+	}
+	Ok(serde_json::from_value(value)?)
 }
 
+/// A version of a component, as written to `<id>/<version>.json`.
+///
+/// When a field is renamed or moved, the old name is kept as a `#[serde(alias = "...")]` for at
+/// least one release cycle, so that components written by older generators still deserialize.
+/// Changes that can't be expressed like that bump [FORMAT_VERSION] and add a migration to
+/// [upgrade]. Unknown fields are only rejected in the current format, after [upgrade]. Files
+/// written by the first generator are kept in `tests/fixtures/v1` to check that they still load.
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct Component {
 	pub format_version: u32,
 	pub id: String,
 	pub version: String,
-	#[serde(skip_serializing_if = "Vec::is_empty", default)]
-	pub requires: Vec<ComponentDependency>,
+	/// The channel the version was released on, such as `release` or `snapshot` for Minecraft.
+	/// `None` if the source doesn't distinguish between channels.
+	pub channel: Option<String>,
+	#[serde(flatten)]
+	pub dependencies: Dependencies,
+	/// Traits that apply on every platform.
 	#[serde(skip_serializing_if = "BTreeSet::is_empty", default)]
 	pub traits: BTreeSet<Trait>,
-	pub assets: Option<Assets>,
 	#[serde(skip_serializing_if = "Vec::is_empty", default)]
-	pub conflicts: Vec<ComponentDependency>,
+	pub platform_traits: Vec<PlatformTrait>,
+	pub assets: Option<Assets>,
+	pub logging: Option<Logging>,
 	pub downloads: Vec<Download>,
+	/// The `-sources` and `-javadoc` jars of libraries, for development environments. These are
+	/// not referenced anywhere else, and launchers only download them when asked to.
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub dev_downloads: Vec<Download>,
 	#[serde(skip_serializing_if = "Vec::is_empty", default)]
 	pub jarmods: Vec<GradleSpecifier>,
 	pub game_jar: Option<GradleSpecifier>, // separate from classpath to make injecting jarmods possible
 	pub main_class: Option<String>,
+	/// The name of the Mojang Java runtime this component expects, e.g. `java-runtime-gamma`.
+	/// This is the version of the matching `com.mojang.java-runtime` component.
+	pub java_component: Option<String>,
+	/// The lowest Java major version this component runs on, e.g. `17`.
+	pub min_java_version: Option<u32>,
 	#[serde(skip_serializing_if = "Vec::is_empty", default)]
 	pub game_arguments: Vec<MinecraftArgument>,
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub jvm_arguments: Vec<MinecraftArgument>,
+	/// LaunchWrapper tweak classes, in order. They are not repeated in `game_arguments`; the
+	/// launcher passes each one as `--tweakClass` after merging the tweakers of all components.
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub tweakers: Vec<String>,
 	pub classpath: Vec<ConditionalClasspathEntry>,
 	#[serde(skip_serializing_if = "Vec::is_empty", default)]
 	pub natives: Vec<Native>,
+	#[serde(skip_serializing_if = "Vec::is_empty", default)]
+	pub java_runtimes: Vec<JavaRuntime>,
+	pub installer: Option<Installer>,
 	pub release_time: DateTime<Utc>,
 }
+
+impl Component {
+	/// Creates a component of the current [FORMAT_VERSION] with every other field empty.
+	pub fn new(
+		id: impl Into<String>,
+		version: impl Into<String>,
+		release_time: DateTime<Utc>,
+	) -> Self {
+		Self {
+			format_version: FORMAT_VERSION,
+			id: id.into(),
+			version: version.into(),
+			channel: None,
+			dependencies: Dependencies::default(),
+			traits: BTreeSet::new(),
+			platform_traits: vec![],
+			assets: None,
+			logging: None,
+			downloads: vec![],
+			dev_downloads: vec![],
+			jarmods: vec![],
+			game_jar: None,
+			main_class: None,
+			java_component: None,
+			min_java_version: None,
+			game_arguments: vec![],
+			jvm_arguments: vec![],
+			tweakers: vec![],
+			classpath: vec![],
+			natives: vec![],
+			java_runtimes: vec![],
+			installer: None,
+			release_time,
+		}
+	}
+
+	/// Finds every artifact that is downloaded more than once with different sizes or hashes,
+	/// ordered by name.
+	pub fn download_conflicts(&self) -> Vec<DownloadConflict> {
+		let mut by_name: BTreeMap<&GradleSpecifier, Vec<&Download>> = BTreeMap::new();
+		for download in &self.downloads {
+			by_name.entry(&download.name).or_default().push(download);
+		}
+		by_name
+			.into_iter()
+			.filter(|(_, downloads)| {
+				downloads.iter().any(|download| {
+					download.size != downloads[0].size || download.hash != downloads[0].hash
+				})
+			})
+			.map(|(name, downloads)| DownloadConflict {
+				name: name.clone(),
+				variants: downloads
+					.into_iter()
+					.map(|download| (download.size, download.hash.clone()))
+					.collect(),
+			})
+			.collect()
+	}
+
+	/// Checks that every artifact the component refers to is downloaded exactly once, or comes
+	/// from its [Installer], and that the format version is [FORMAT_VERSION].
+	pub fn validate(&self) -> Result<(), ValidationError> {
+		if self.format_version != FORMAT_VERSION {
+			return Err(ValidationError::UnsupportedFormatVersion(
+				self.format_version,
+			));
+		}
+		let conflicts = self.download_conflicts();
+		if !conflicts.is_empty() {
+			return Err(ValidationError::ConflictingDownloads(conflicts));
+		}
+		let mut downloads = HashSet::with_capacity(self.downloads.len());
+		for download in &self.downloads {
+			if !downloads.insert(&download.name) {
+				return Err(ValidationError::DuplicateDownload(download.name.clone()));
+			}
+		}
+		if let Some(installer) = &self.installer {
+			if !downloads.contains(&installer.installer) {
+				return Err(ValidationError::MissingInstallerDownload(
+					installer.installer.clone(),
+				));
+			}
+			downloads.extend(&installer.bundled);
+			for processor in &installer.processors {
+				for name in std::iter::once(&processor.jar).chain(&processor.classpath) {
+					if !downloads.contains(name) {
+						return Err(ValidationError::MissingProcessorDownload(name.clone()));
+					}
+				}
+			}
+			// the processors run before launching, so what they write can be on the classpath
+			downloads.extend(&installer.generated);
+		}
+		for entry in &self.classpath {
+			let (ConditionalClasspathEntry::All(name)
+			| ConditionalClasspathEntry::PlatformSpecific { name, .. }) = entry;
+			if !downloads.contains(name) {
+				return Err(ValidationError::MissingClasspathDownload(name.clone()));
+			}
+		}
+		for native in &self.natives {
+			if !downloads.contains(&native.name) {
+				return Err(ValidationError::MissingNativeDownload(native.name.clone()));
+			}
+		}
+		for jarmod in &self.jarmods {
+			if !downloads.contains(jarmod) {
+				return Err(ValidationError::MissingJarmodDownload(jarmod.clone()));
+			}
+		}
+		if let Some(game_jar) = &self.game_jar {
+			if !downloads.contains(game_jar) {
+				return Err(ValidationError::MissingGameJarDownload(game_jar.clone()));
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns the features this component's game arguments can be toggled by.
+	pub fn supported_features(&self) -> BTreeSet<ConditionFeature> {
+		self.game_arguments
+			.iter()
+			.filter_map(|argument| match argument {
+				MinecraftArgument::Always(_) | MinecraftArgument::PlatformSpecific { .. } => None,
+				MinecraftArgument::Conditional { feature, .. } => Some(*feature),
+			})
+			.collect()
+	}
+
+	/// Returns the natives to extract when launching on `os` and `arch`.
+	pub fn natives_for(&self, os: OsName, arch: Arch) -> Vec<&Native> {
+		self.natives
+			.iter()
+			.filter(|native| native.platform.matches(os, Some(arch)))
+			.collect()
+	}
+
+	/// Returns the union of the extraction exclusions of [Component::natives_for].
+	pub fn native_exclusions_for(&self, os: OsName, arch: Arch) -> BTreeSet<&str> {
+		self.natives_for(os, arch)
+			.into_iter()
+			.flat_map(|native| native.exclusions.iter().map(String::as_str))
+			.collect()
+	}
+}
+
+/// Returns the main class to launch a merged set of components with.
+///
+/// `components` must be in dependency order, dependencies first. The last component that sets a
+/// main class wins; components without one, such as mappings, are skipped.
+pub fn effective_main_class(components: &[Component]) -> Option<&str> {
+	components
+		.iter()
+		.rev()
+		.find_map(|component| component.main_class.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A component written by the first version of the generator, before `requires` and
+	/// `conflicts` were grouped and channels were added, has to keep loading, and has to load the
+	/// same after being written again in the current format.
+	#[test]
+	fn v1_component_round_trips() {
+		let value: serde_json::Value =
+			serde_json::from_str(include_str!("../tests/fixtures/v1/component.json")).unwrap();
+		let component = upgrade(value).unwrap();
+		assert_eq!(component.version, "1.19");
+		assert_eq!(component.channel, None);
+		component.validate().unwrap();
+
+		let written = serde_json::to_value(&component).unwrap();
+		let reread = upgrade(written.clone()).unwrap();
+		assert_eq!(serde_json::to_value(&reread).unwrap(), written);
+	}
+
+	#[test]
+	fn unsupported_format_versions_are_rejected() {
+		let mut value: serde_json::Value =
+			serde_json::from_str(include_str!("../tests/fixtures/v1/component.json")).unwrap();
+		value["format_version"] = 2.into();
+		assert!(matches!(
+			upgrade(value),
+			Err(UpgradeError::UnsupportedFormatVersion(2))
+		));
+	}
+
+	#[test]
+	fn the_last_main_class_wins() {
+		let component = |id: &str, main_class: Option<&str>| Component {
+			main_class: main_class.map(str::to_owned),
+			..Component::new(id, "1.19", DateTime::UNIX_EPOCH)
+		};
+		let vanilla = component("net.minecraft", Some("net.minecraft.client.main.Main"));
+		let intermediary = component("net.fabricmc.intermediary", None);
+		let loader = component(
+			"net.fabricmc.fabric-loader",
+			Some("net.fabricmc.loader.impl.launch.knot.KnotClient"),
+		);
+
+		let mut components = vec![vanilla, intermediary];
+		assert_eq!(
+			effective_main_class(&components),
+			Some("net.minecraft.client.main.Main")
+		);
+		components.push(loader);
+		assert_eq!(
+			effective_main_class(&components),
+			Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+		);
+		assert_eq!(effective_main_class(&[]), None);
+	}
+
+	#[test]
+	fn natives_are_picked_by_arch() {
+		let native = |classifier: &str, arch, exclusion: &str| Native {
+			name: format!("org.lwjgl:lwjgl:3.3.1:{classifier}")
+				.parse()
+				.unwrap(),
+			platform: Platform {
+				os: vec![OsName::Osx],
+				arch: Some(arch),
+				os_version: None,
+			},
+			exclusions: vec![exclusion.into()],
+		};
+		let component = Component {
+			natives: vec![
+				native("natives-macos", Arch::X86_64, "META-INF/"),
+				native("natives-macos-arm64", Arch::Arm64, "module-info.class"),
+			],
+			..Component::new("net.minecraft", "1.19", DateTime::UNIX_EPOCH)
+		};
+		let classifiers = |arch| -> Vec<_> {
+			component
+				.natives_for(OsName::Osx, arch)
+				.into_iter()
+				.map(|native| native.name.classifier.as_deref().unwrap())
+				.collect()
+		};
+
+		assert_eq!(classifiers(Arch::X86_64), ["natives-macos"]);
+		assert_eq!(classifiers(Arch::Arm64), ["natives-macos-arm64"]);
+		assert!(component
+			.natives_for(OsName::Linux, Arch::X86_64)
+			.is_empty());
+		assert_eq!(
+			component.native_exclusions_for(OsName::Osx, Arch::Arm64),
+			BTreeSet::from(["module-info.class"])
+		);
+		// Rosetta is not considered, so an arm64 host doesn't fall back to x86_64 natives, while
+		// an x86_64 host can still run x86 ones
+		assert!(!Arch::Arm64.can_run(Arch::X86_64));
+		assert!(Arch::X86_64.can_run(Arch::X86));
+		assert!(!Arch::X86.can_run(Arch::X86_64));
+	}
+
+	#[test]
+	fn supported_features_come_from_conditional_arguments() {
+		let component = Component {
+			game_arguments: vec![
+				MinecraftArgument::Always("--username".into()),
+				MinecraftArgument::Conditional {
+					value: "--demo".into(),
+					feature: ConditionFeature::Demo,
+				},
+				MinecraftArgument::Conditional {
+					value: "--width".into(),
+					feature: ConditionFeature::CustomResolution,
+				},
+				MinecraftArgument::Conditional {
+					value: "--height".into(),
+					feature: ConditionFeature::CustomResolution,
+				},
+				MinecraftArgument::PlatformSpecific {
+					value: "-XstartOnFirstThread".into(),
+					platform: Platform {
+						os: vec![OsName::Osx],
+						arch: None,
+						os_version: None,
+					},
+				},
+			],
+			..Component::new("net.minecraft", "1.19", DateTime::UNIX_EPOCH)
+		};
+		assert_eq!(
+			component.supported_features(),
+			BTreeSet::from([ConditionFeature::Demo, ConditionFeature::CustomResolution])
+		);
+	}
+
+	#[test]
+	fn sha512_round_trips() {
+		let hex = "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043";
+		let hash = Hash::parse_sha512(hex).unwrap();
+		assert_eq!(hash.algorithm(), HashAlgorithm::Sha512);
+
+		let written = serde_json::to_value(&hash).unwrap();
+		assert_eq!(written, serde_json::json!({ "sha512": hex }));
+		assert_eq!(serde_json::from_value::<Hash>(written).unwrap(), hash);
+	}
+
+	#[test]
+	fn hashes_of_the_wrong_length_are_rejected() {
+		assert!(matches!(
+			Hash::parse_sha1("aaf4c61ddcc5e8a2dabede0f3b482cd9aea943"),
+			Err(HashParseError::WrongLength { expected: 20, .. })
+		));
+		assert!(matches!(
+			Hash::parse_sha256("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"),
+			Err(HashParseError::WrongLength { expected: 32, .. })
+		));
+		assert!(serde_json::from_value::<Hash>(serde_json::json!({ "sha1": "aaf4" })).is_err());
+	}
+
+	#[test]
+	fn upper_case_hashes_are_normalized() {
+		let upper = Hash::parse_sha1("AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D").unwrap();
+		let lower = Hash::parse_sha1("aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d").unwrap();
+		assert_eq!(upper, lower);
+		assert_eq!(upper.hex(), "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
+		assert_eq!(
+			serde_json::to_value(&upper).unwrap(),
+			serde_json::json!({ "sha1": "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d" })
+		);
+	}
+
+	#[test]
+	fn provides_round_trips() {
+		let component = Component {
+			dependencies: Dependencies {
+				requires: vec![ComponentDependency {
+					id: "net.minecraft".into(),
+					version: Some("1.12.2".into()),
+				}],
+				provides: vec![ComponentDependency {
+					id: "net.fabricmc.intermediary".into(),
+					version: Some("1.12.2".into()),
+				}],
+				..Default::default()
+			},
+			..Component::new(
+				"net.legacyfabric.intermediary",
+				"1.12.2",
+				DateTime::UNIX_EPOCH,
+			)
+		};
+
+		let written = serde_json::to_value(&component).unwrap();
+		assert_eq!(
+			written["provides"],
+			serde_json::json!([{ "id": "net.fabricmc.intermediary", "version": "1.12.2" }])
+		);
+		let reread: Component = serde_json::from_value(written.clone()).unwrap();
+		assert_eq!(reread.dependencies.provides.len(), 1);
+		assert_eq!(
+			reread.dependencies.provides[0].id,
+			"net.fabricmc.intermediary"
+		);
+		assert_eq!(serde_json::to_value(&reread).unwrap(), written);
+	}
+
+	#[test]
+	fn traits_round_trip() {
+		for (kind, name, alias) in [
+			(
+				Trait::MacStartOnFirstThread,
+				"mac_start_on_first_thread",
+				Some("MacStartOnFirstThread"),
+			),
+			(
+				Trait::SupportsCustomResolution,
+				"supports_custom_resolution",
+				Some("SupportsCustomResolution"),
+			),
+			(
+				Trait::SupportsQuickPlayServerLegacy,
+				"supports_quick_play_server_legacy",
+				Some("SupportsQuickPlayServerLegacy"),
+			),
+			(
+				Trait::SupportsQuickPlayServer,
+				"supports_quick_play_server",
+				Some("SupportsQuickPlayServer"),
+			),
+			(
+				Trait::SupportsQuickPlayWorld,
+				"supports_quick_play_world",
+				Some("SupportsQuickPlayWorld"),
+			),
+			(Trait::LegacyAssets, "legacy_assets", None),
+		] {
+			assert_eq!(serde_json::to_value(kind).unwrap(), name);
+			assert_eq!(serde_json::from_value::<Trait>(name.into()).unwrap(), kind);
+			if let Some(alias) = alias {
+				assert_eq!(serde_json::from_value::<Trait>(alias.into()).unwrap(), kind);
+			}
+		}
+	}
+
+	#[test]
+	fn arches_round_trip() {
+		for (arch, name) in [
+			(Arch::X86, "x86"),
+			(Arch::X86_64, "x86_64"),
+			(Arch::Arm32, "arm32"),
+			(Arch::Arm64, "arm64"),
+			(Arch::Riscv64, "riscv64"),
+		] {
+			assert_eq!(serde_json::to_value(arch).unwrap(), name);
+			assert_eq!(serde_json::from_value::<Arch>(name.into()).unwrap(), arch);
+		}
+	}
+
+	#[test]
+	fn platforms_without_os_match_any_os() {
+		let platform = Platform {
+			os: vec![],
+			arch: None,
+			os_version: None,
+		};
+		for os in [OsName::Linux, OsName::Osx, OsName::Windows] {
+			assert!(platform.matches(os, None));
+			assert!(platform.matches(os, Some(Arch::Arm64)));
+		}
+	}
+
+	#[test]
+	fn arch_specific_platforms_match_their_arch() {
+		let platform = Platform {
+			os: vec![OsName::Osx],
+			arch: Some(Arch::Arm64),
+			os_version: None,
+		};
+		assert!(platform.matches(OsName::Osx, Some(Arch::Arm64)));
+		// an unknown host arch matches any arch
+		assert!(platform.matches(OsName::Osx, None));
+		assert!(!platform.matches(OsName::Osx, Some(Arch::X86_64)));
+		assert!(!platform.matches(OsName::Linux, Some(Arch::Arm64)));
+
+		let name: GradleSpecifier = "org.lwjgl:lwjgl:3.3.1:natives-macos-arm64".parse().unwrap();
+		let entry = ConditionalClasspathEntry::PlatformSpecific {
+			name: name.clone(),
+			platform,
+		};
+		assert!(entry.applies_to(OsName::Osx, Some(Arch::Arm64)));
+		assert!(!entry.applies_to(OsName::Osx, Some(Arch::X86_64)));
+		assert!(ConditionalClasspathEntry::All(name).applies_to(OsName::Linux, Some(Arch::X86)));
+	}
+}
@@ -6,38 +6,84 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
 
 use super::component;
 
 pub type Index = Vec<IndexEntry>;
 
+#[serde_as]
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct IndexEntry {
 	pub version: String,
 	pub release_time: DateTime<Utc>,
+	pub channel: Option<String>,
+	/// The SHA-256 of the component file, as written, so that clients can tell whether a
+	/// component changed without fetching it. `None` in indexes written before it was recorded.
+	#[serde_as(as = "Option<Hex>")]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	#[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+	pub sha256: Option<[u8; 32]>,
+	/// The channels this version is the latest of, as designated by the source, e.g. `release`
+	/// and `snapshot` for Minecraft. Most sources don't designate any.
 	#[serde(skip_serializing_if = "Vec::is_empty", default)]
-	pub conflicts: Vec<component::ComponentDependency>,
-	#[serde(skip_serializing_if = "Vec::is_empty", default)]
-	pub requires: Vec<component::ComponentDependency>,
+	pub latest: Vec<String>,
+	#[serde(flatten)]
+	pub dependencies: component::Dependencies,
 }
 
-impl From<&component::Component> for IndexEntry {
-	fn from(component: &component::Component) -> Self {
+impl IndexEntry {
+	/// Creates the entry of `component`, whose file hashes to `sha256`.
+	pub fn new(component: &component::Component, sha256: [u8; 32]) -> Self {
 		Self {
-			version: component.version.to_string(),
-			conflicts: component.conflicts.to_vec(),
-			requires: component.requires.to_vec(),
+			version: component.version.clone(),
 			release_time: component.release_time,
+			channel: component.channel.clone(),
+			sha256: Some(sha256),
+			latest: vec![],
+			dependencies: component.dependencies.clone(),
 		}
 	}
 }
-impl From<component::Component> for IndexEntry {
-	fn from(component: component::Component) -> Self {
-		Self {
-			version: component.version,
-			conflicts: component.conflicts,
-			requires: component.requires,
-			release_time: component.release_time,
-		}
+
+/// An entry of the search index, which flattens the indexes of all components into a single list.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SearchEntry {
+	pub id: String,
+	#[serde(flatten)]
+	pub entry: IndexEntry,
+}
+
+pub type SearchIndex = Vec<SearchEntry>;
+
+/// An entry of the top-level index, which lists every component with a summary of its index.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ComponentSummary {
+	pub id: String,
+	/// The path of the index of the component, relative to the top-level index.
+	pub index: String,
+	pub latest_version: String,
+	pub latest_release_time: DateTime<Utc>,
+}
+
+pub type ComponentIndex = Vec<ComponentSummary>;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// An index written by the first version of the generator, before versions had channels or
+	/// hashes.
+	#[test]
+	fn v1_index_parses() {
+		let index: Index =
+			serde_json::from_str(include_str!("../tests/fixtures/v1/index.json")).unwrap();
+		assert_eq!(index.len(), 2);
+		assert_eq!(index[0].version, "1.19");
+		assert_eq!(index[0].sha256, None);
+		assert!(index[0].latest.is_empty());
 	}
 }
@@ -6,4 +6,6 @@
 
 pub mod component;
 pub mod index;
+#[cfg(feature = "schemars")]
+pub mod schema;
 pub mod util;
@@ -0,0 +1,26 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! JSON Schemas of the files written by the generator, for validating them outside of Rust.
+
+use schemars::{schema::RootSchema, schema_for};
+
+use crate::{component, index};
+
+/// The schema of a component file, such as `net.minecraft/1.20.1.json`.
+pub fn component() -> RootSchema {
+	schema_for!(component::Component)
+}
+
+/// The schema of the `index.json` of a component.
+pub fn index() -> RootSchema {
+	schema_for!(index::Index)
+}
+
+/// The schema of the top-level `index.json`.
+pub fn component_index() -> RootSchema {
+	schema_for!(index::ComponentIndex)
+}
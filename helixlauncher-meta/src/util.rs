@@ -4,14 +4,25 @@
  * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, fmt::Display, path::PathBuf, str::FromStr};
 
+use chrono::NaiveDateTime;
+use maven_version::Maven3ArtifactVersion;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use thiserror::Error;
 
 use crate::component;
 
-#[derive(Debug, DeserializeFromStr, SerializeDisplay, Hash, Clone, PartialEq, Eq)]
+/// A Maven artifact coordinate in Gradle notation,
+/// `group:artifact:version[:classifier][@extension]`.
+///
+/// The [Ord] impl compares the fields lexicographically, in declaration order, so that lists of
+/// specifiers sort deterministically. It does not order versions semantically; use
+/// [GradleSpecifier::version_cmp] for that.
+#[derive(
+	Debug, DeserializeFromStr, SerializeDisplay, Hash, Clone, PartialEq, Eq, PartialOrd, Ord,
+)]
 pub struct GradleSpecifier {
 	pub group: String,
 	pub artifact: String,
@@ -27,6 +38,117 @@ impl GradleSpecifier {
 			..self.clone()
 		}
 	}
+
+	/// Compares the versions of two builds of the same artifact with Maven's ordering rules, so
+	/// that `1.0-beta.4` < `1.0` < `1.0.1`. Returns `None` if the group, artifact or classifier
+	/// differ.
+	pub fn version_cmp(&self, other: &Self) -> Option<Ordering> {
+		if self.group != other.group
+			|| self.artifact != other.artifact
+			|| self.classifier != other.classifier
+		{
+			return None;
+		}
+		Some(
+			Maven3ArtifactVersion::new(&self.version)
+				.cmp(&Maven3ArtifactVersion::new(&other.version)),
+		)
+	}
+
+	/// Parses the version as a resolved snapshot like `1.0.0-20230101.123456-7`, returning `None`
+	/// for any other version.
+	pub fn snapshot(&self) -> Option<SnapshotVersion> {
+		let (version, build) = self.version.rsplit_once('-')?;
+		let (base, timestamp) = version.rsplit_once('-')?;
+		if base.is_empty() || !build.bytes().all(|b| b.is_ascii_digit()) {
+			return None;
+		}
+		Some(SnapshotVersion {
+			base: format!("{base}-SNAPSHOT"),
+			timestamp: NaiveDateTime::parse_from_str(timestamp, "%Y%m%d.%H%M%S").ok()?,
+			build: build.parse().ok()?,
+		})
+	}
+
+	/// The directories and file name of this artifact in a Maven repository. Resolved snapshots
+	/// live in the directory of their base version.
+	fn path_segments(&self) -> Vec<String> {
+		let mut segments: Vec<String> = self.group.split('.').map(str::to_owned).collect();
+		segments.push(self.artifact.clone());
+		segments.push(match self.snapshot() {
+			Some(snapshot) => snapshot.base,
+			None => self.version.clone(),
+		});
+		segments.push(self.file_name());
+		segments
+	}
+
+	/// Returns the file name of this artifact, `artifact-version[-classifier].extension`.
+	pub fn file_name(&self) -> String {
+		let mut file_name = format!("{}-{}", self.artifact, self.version);
+		if let Some(classifier) = &self.classifier {
+			file_name.push('-');
+			file_name.push_str(classifier);
+		}
+		file_name.push('.');
+		file_name.push_str(&self.extension);
+		file_name
+	}
+
+	/// Returns the path of this artifact relative to the root of a Maven repository, e.g.
+	/// `org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar`.
+	pub fn to_path(&self) -> PathBuf {
+		self.path_segments().into_iter().collect()
+	}
+
+	/// Returns the URL of this artifact in the Maven repository at `base`, with or without a
+	/// trailing `/`. Each path segment is percent-encoded, so that e.g. the `+` in
+	/// `1.20.1+build.2` isn't read as a space.
+	pub fn to_url(&self, base: &str) -> String {
+		let path = self
+			.path_segments()
+			.iter()
+			.map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+			.collect::<Vec<_>>()
+			.join("/");
+		format!("{}/{path}", base.trim_end_matches('/'))
+	}
+}
+
+/// Specifiers are strings in JSON, so their schema is that of a string in Gradle notation.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for GradleSpecifier {
+	fn schema_name() -> String {
+		"GradleSpecifier".into()
+	}
+
+	fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+		schemars::schema::SchemaObject {
+			instance_type: Some(schemars::schema::InstanceType::String.into()),
+			string: Some(Box::new(schemars::schema::StringValidation {
+				pattern: Some("^[^:]+:[^:]+:[^:@]+(:[^@]+)?(@[^@]+)?$".into()),
+				..Default::default()
+			})),
+			..Default::default()
+		}
+		.into()
+	}
+}
+
+/// Everything but the unreserved characters of RFC 3986.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+	.remove(b'-')
+	.remove(b'.')
+	.remove(b'_')
+	.remove(b'~');
+
+/// A resolved Maven snapshot version, as returned by [GradleSpecifier::snapshot].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotVersion {
+	/// The unresolved version, e.g. `1.0.0-SNAPSHOT`.
+	pub base: String,
+	pub timestamp: NaiveDateTime,
+	pub build: u32,
 }
 
 #[derive(Error, Debug)]
@@ -35,18 +157,24 @@ pub enum GradleParseError {
 	ArtifactIdMissing(String),
 	#[error("\"{0}\" does not contain a version!")]
 	VersionMissing(String),
+	#[error("\"{0}\" has an empty group!")]
+	EmptyGroup(String),
+	#[error("\"{0}\" has an empty artifact id!")]
+	EmptyArtifact(String),
+	#[error("\"{0}\" has an empty version!")]
+	EmptyVersion(String),
 }
 
 impl FromStr for GradleSpecifier {
 	type Err = GradleParseError;
 
-	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let (group, s) = s
+	fn from_str(input: &str) -> Result<Self, Self::Err> {
+		let (group, s) = input
 			.split_once(':')
-			.ok_or_else(|| GradleParseError::ArtifactIdMissing(s.to_string()))?;
+			.ok_or_else(|| GradleParseError::ArtifactIdMissing(input.to_string()))?;
 		let (artifact, s) = s
 			.split_once(':')
-			.ok_or_else(|| GradleParseError::VersionMissing(s.to_string()))?;
+			.ok_or_else(|| GradleParseError::VersionMissing(input.to_string()))?;
 		let (s, extension) = s
 			.rsplit_once('@')
 			.map_or_else(|| (s, "jar"), |(s, extension)| (s, extension));
@@ -55,6 +183,16 @@ impl FromStr for GradleSpecifier {
 			|(version, classifier)| (version, Some(classifier)),
 		);
 
+		if group.is_empty() {
+			return Err(GradleParseError::EmptyGroup(input.to_string()));
+		}
+		if artifact.is_empty() {
+			return Err(GradleParseError::EmptyArtifact(input.to_string()));
+		}
+		if version.is_empty() {
+			return Err(GradleParseError::EmptyVersion(input.to_string()));
+		}
+
 		Ok(GradleSpecifier {
 			group: group.to_owned(),
 			artifact: artifact.to_owned(),
@@ -96,9 +234,206 @@ cfg_if::cfg_if! {
 		pub const CURRENT_ARCH: component::Arch = component::Arch::X86;
 	} else if #[cfg(target_arch = "x86_64")] {
 		pub const CURRENT_ARCH: component::Arch = component::Arch::X86_64;
+	} else if #[cfg(target_arch = "arm")] {
+		pub const CURRENT_ARCH: component::Arch = component::Arch::Arm32;
 	} else if #[cfg(target_arch = "aarch64")] {
 		pub const CURRENT_ARCH: component::Arch = component::Arch::Arm64;
+	} else if #[cfg(target_arch = "riscv64")] {
+		pub const CURRENT_ARCH: component::Arch = component::Arch::Riscv64;
 	} else {
 		compile_error!("Unsupported CPU architecture");
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use chrono::NaiveDate;
+
+	use super::*;
+
+	fn parse(specifier: &str) -> GradleSpecifier {
+		specifier.parse().unwrap()
+	}
+
+	#[test]
+	fn parses_every_part() {
+		let specifier = parse("org.lwjgl:lwjgl:3.3.1:natives-linux@zip");
+		assert_eq!(specifier.group, "org.lwjgl");
+		assert_eq!(specifier.artifact, "lwjgl");
+		assert_eq!(specifier.version, "3.3.1");
+		assert_eq!(specifier.classifier.as_deref(), Some("natives-linux"));
+		assert_eq!(specifier.extension, "zip");
+
+		let specifier = parse("net.fabricmc:fabric-loader:0.15.11");
+		assert_eq!(specifier.classifier, None);
+		assert_eq!(specifier.extension, "jar");
+	}
+
+	#[test]
+	fn display_round_trips() {
+		for specifier in [
+			"net.fabricmc:fabric-loader:0.15.11",
+			"org.lwjgl:lwjgl:3.3.1:natives-linux",
+			"net.minecraftforge:forge:1.20.1-47.2.0:installer@zip",
+		] {
+			assert_eq!(parse(specifier).to_string(), specifier);
+		}
+	}
+
+	#[test]
+	fn to_url_encodes_path_segments() {
+		assert_eq!(
+			parse("net.fabricmc:fabric-api:0.92.0+1.20.1").to_url("https://maven.fabricmc.net/"),
+			"https://maven.fabricmc.net/net/fabricmc/fabric-api/0.92.0%2B1.20.1/fabric-api-0.92.0%2B1.20.1.jar"
+		);
+		assert_eq!(
+			parse("net.fabricmc:intermediary:1.14 Pre-Release 1").to_url("https://maven.fabricmc.net"),
+			"https://maven.fabricmc.net/net/fabricmc/intermediary/1.14%20Pre-Release%201/intermediary-1.14%20Pre-Release%201.jar"
+		);
+	}
+
+	#[test]
+	fn to_path_is_unencoded() {
+		assert_eq!(
+			parse("net.fabricmc:fabric-api:0.92.0+1.20.1").to_path(),
+			PathBuf::from("net/fabricmc/fabric-api/0.92.0+1.20.1/fabric-api-0.92.0+1.20.1.jar")
+		);
+		assert_eq!(
+			parse("net.minecraftforge:forge:1.20.1-47.2.0:installer@zip").to_path(),
+			PathBuf::from(
+				"net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-installer.zip"
+			)
+		);
+	}
+
+	#[test]
+	fn parses_snapshots() {
+		let specifier = parse("com.example:lib:1.0.0-20230101.123456-7");
+		assert_eq!(
+			specifier.snapshot(),
+			Some(SnapshotVersion {
+				base: "1.0.0-SNAPSHOT".into(),
+				timestamp: NaiveDate::from_ymd_opt(2023, 1, 1)
+					.unwrap()
+					.and_hms_opt(12, 34, 56)
+					.unwrap(),
+				build: 7,
+			})
+		);
+		assert_eq!(
+			specifier.to_url("https://maven.example.com"),
+			"https://maven.example.com/com/example/lib/1.0.0-SNAPSHOT/lib-1.0.0-20230101.123456-7.jar"
+		);
+
+		for version in [
+			"1.0.0",
+			"1.0.0-rc-1",
+			"1.0.0-20230101.123456-x",
+			"-20230101.123456-7",
+		] {
+			assert_eq!(
+				parse(&format!("com.example:lib:{version}")).snapshot(),
+				None,
+				"{version}"
+			);
+		}
+	}
+
+	#[test]
+	fn rejects_incomplete_specifiers() {
+		let parse_err = |specifier: &str| specifier.parse::<GradleSpecifier>().unwrap_err();
+		assert!(matches!(
+			parse_err("org.lwjgl"),
+			GradleParseError::ArtifactIdMissing(_)
+		));
+		assert!(matches!(
+			parse_err("org.lwjgl:lwjgl"),
+			GradleParseError::VersionMissing(_)
+		));
+		assert!(matches!(
+			parse_err(":lwjgl:3.3.1"),
+			GradleParseError::EmptyGroup(_)
+		));
+		assert!(matches!(
+			parse_err("org.lwjgl::3.3.1"),
+			GradleParseError::EmptyArtifact(_)
+		));
+		assert!(matches!(
+			parse_err("org.lwjgl:lwjgl:"),
+			GradleParseError::EmptyVersion(_)
+		));
+		assert!(matches!(
+			parse_err("org.lwjgl:lwjgl::natives-linux"),
+			GradleParseError::EmptyVersion(_)
+		));
+	}
+
+	#[test]
+	fn sorts_by_group_artifact_version_and_classifier() {
+		let mut specifiers = [
+			parse("org.lwjgl:lwjgl:3.3.1:natives-linux"),
+			parse("org.lwjgl:lwjgl:3.3.1"),
+			parse("com.mojang:brigadier:1.0.18"),
+			parse("org.lwjgl:lwjgl:3.2.2"),
+		];
+		specifiers.sort();
+		let sorted: Vec<_> = specifiers.iter().map(ToString::to_string).collect();
+		assert_eq!(
+			sorted,
+			[
+				"com.mojang:brigadier:1.0.18",
+				"org.lwjgl:lwjgl:3.2.2",
+				"org.lwjgl:lwjgl:3.3.1",
+				"org.lwjgl:lwjgl:3.3.1:natives-linux",
+			]
+		);
+	}
+
+	#[test]
+	fn compares_versions_semantically() {
+		let beta = parse("com.example:lib:1.0-beta.4");
+		let release = parse("com.example:lib:1.0");
+		let patch = parse("com.example:lib:1.0.1");
+		assert_eq!(beta.version_cmp(&release), Some(Ordering::Less));
+		assert_eq!(patch.version_cmp(&release), Some(Ordering::Greater));
+		assert_eq!(release.version_cmp(&parse("com.example:other:1.0")), None);
+	}
+
+	#[test]
+	fn to_url_ignores_trailing_slash() {
+		let specifier = parse("org.lwjgl:lwjgl:3.3.1:natives-linux");
+		let expected =
+			"https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar";
+		assert_eq!(
+			specifier.to_url("https://libraries.minecraft.net/"),
+			expected
+		);
+		assert_eq!(
+			specifier.to_url("https://libraries.minecraft.net"),
+			expected
+		);
+	}
+
+	#[test]
+	fn file_name_is_the_last_path_component() {
+		for (specifier, file_name) in [
+			("org.lwjgl:lwjgl:3.3.1", "lwjgl-3.3.1.jar"),
+			(
+				"org.lwjgl:lwjgl:3.3.1:natives-linux",
+				"lwjgl-3.3.1-natives-linux.jar",
+			),
+			("com.mojang:minecraft:1.19@json", "minecraft-1.19.json"),
+			(
+				"net.minecraftforge:forge:1.20.1-47.2.0:installer@zip",
+				"forge-1.20.1-47.2.0-installer.zip",
+			),
+		] {
+			let specifier = parse(specifier);
+			assert_eq!(specifier.file_name(), file_name);
+			assert_eq!(
+				specifier.to_path().file_name().unwrap().to_str(),
+				Some(file_name)
+			);
+		}
+	}
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{env, path::PathBuf};
+
+use anyhow::{ensure, Context, Result};
+
+/// Settings shared by every source.
+#[derive(Debug)]
+pub struct Config {
+	/// How many requests a fetcher runs at the same time.
+	pub concurrency: usize,
+	/// Whether the Mojang and Fabric sources look up the `-sources` and `-javadoc` jars of their
+	/// libraries, see [helixlauncher_meta::component::Component::dev_downloads]. Set by the
+	/// `--dev-downloads` flag rather than the environment.
+	pub dev_downloads: bool,
+	pub paths: Paths,
+}
+
+/// Where the generator keeps its data.
+#[derive(Debug)]
+pub struct Paths {
+	/// Downloaded upstream metadata, `upstream` by default.
+	pub upstream_root: PathBuf,
+	/// Generated components, `out` by default.
+	pub out_root: PathBuf,
+	/// Components generated from files on this machine, such as OptiFine jars, which must not be
+	/// published with the others, `local` by default.
+	pub local_root: PathBuf,
+	/// The replacement downloads for Mojang libraries, `overrides.json` by default.
+	pub overrides: PathBuf,
+	/// Additional Mojang argument placeholder mappings, `placeholders.json` by default. The file
+	/// is optional.
+	pub placeholders: PathBuf,
+	/// Where `--errors-json` writes the failures, `errors.json` by default.
+	pub errors_json: PathBuf,
+}
+
+impl Config {
+	/// Reads the configuration from the environment, using the defaults for anything unset.
+	/// `HELIX_META_CONCURRENCY` sets [Config::concurrency], `HELIX_META_UPSTREAM_DIR`,
+	/// `HELIX_META_OUT_DIR`, `HELIX_META_LOCAL_DIR`, `HELIX_META_OVERRIDES`,
+	/// `HELIX_META_PLACEHOLDERS` and `HELIX_META_ERRORS_JSON` set [Config::paths].
+	pub fn from_env() -> Result<Self> {
+		let concurrency = match env::var("HELIX_META_CONCURRENCY") {
+			Ok(concurrency) => concurrency
+				.parse()
+				.with_context(|| format!("Invalid HELIX_META_CONCURRENCY {concurrency}"))?,
+			Err(_) => 5,
+		};
+		ensure!(concurrency > 0, "HELIX_META_CONCURRENCY must be at least 1");
+		let paths = Paths {
+			upstream_root: env::var_os("HELIX_META_UPSTREAM_DIR")
+				.map_or_else(|| "upstream".into(), PathBuf::from),
+			out_root: env::var_os("HELIX_META_OUT_DIR").map_or_else(|| "out".into(), PathBuf::from),
+			local_root: env::var_os("HELIX_META_LOCAL_DIR")
+				.map_or_else(|| "local".into(), PathBuf::from),
+			overrides: env::var_os("HELIX_META_OVERRIDES")
+				.map_or_else(|| "overrides.json".into(), PathBuf::from),
+			placeholders: env::var_os("HELIX_META_PLACEHOLDERS")
+				.map_or_else(|| "placeholders.json".into(), PathBuf::from),
+			errors_json: env::var_os("HELIX_META_ERRORS_JSON")
+				.map_or_else(|| "errors.json".into(), PathBuf::from),
+		};
+		Ok(Self {
+			concurrency,
+			dev_downloads: false,
+			paths,
+		})
+	}
+}
@@ -0,0 +1,366 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use futures::{StreamExt, TryStreamExt};
+use serde::Deserialize;
+
+use helixlauncher_meta as helix;
+use helixlauncher_meta::util::GradleSpecifier;
+
+use crate::{
+	add_dev_downloads, artifact_download, artifact_info_path, config::Config, fetch_artifact_info,
+	fetch_cached, fetch_dev_artifact_infos, index, report::Failures, with_retry,
+};
+
+const META_URL: &str = "https://meta.fabricmc.net/v2/versions/";
+const MAVEN_URL: &str = "https://maven.fabricmc.net/";
+
+/// An entry of the version lists of the Fabric meta, such as `/v2/versions/loader`.
+#[derive(Deserialize, Debug)]
+pub struct MetaVersion {
+	pub maven: GradleSpecifier,
+	pub version: String,
+}
+
+/// The launch profile a loader publishes next to its jar. Unknown keys are allowed, as they
+/// differ between loader versions.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoaderProfile {
+	#[serde(rename = "min_java_version")]
+	pub min_java_version: Option<u32>,
+	pub libraries: LoaderLibraries,
+	pub main_class: LoaderMainClass,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoaderLibraries {
+	#[serde(default)]
+	pub client: Vec<LoaderLibrary>,
+	#[serde(default)]
+	pub common: Vec<LoaderLibrary>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoaderLibrary {
+	pub name: GradleSpecifier,
+	/// The base URL of the Maven repository the library is on.
+	pub url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum LoaderMainClass {
+	Sided { client: String },
+	Always(String),
+}
+
+/// Where a loader keeps its data. Fabric and its forks for other game versions share the meta
+/// and profile formats, and only differ in these.
+pub struct Flavor {
+	/// The name used in failure reports and under the upstream root.
+	pub name: &'static str,
+	pub meta_url: &'static str,
+	pub maven_url: &'static str,
+	pub intermediary_id: &'static str,
+	/// Components the intermediary can stand in for, see [helix::component::Dependencies].
+	pub intermediary_provides: &'static [&'static str],
+	/// `None` if the flavor uses the loader of another flavor.
+	pub loader_id: Option<&'static str>,
+}
+
+pub const FABRIC: Flavor = Flavor {
+	name: "fabric",
+	meta_url: META_URL,
+	maven_url: MAVEN_URL,
+	intermediary_id: "net.fabricmc.intermediary",
+	intermediary_provides: &[],
+	loader_id: Some("net.fabricmc.fabric-loader"),
+};
+
+pub async fn fetch(client: &reqwest::Client, config: &Config, failures: &Failures) -> Result<()> {
+	fetch_flavor(client, config, failures, &FABRIC).await
+}
+
+/// Downloads the version lists and loader profiles of `flavor`, and looks up the hashes, sizes
+/// and release times of every artifact, so that processing needs no network access.
+pub async fn fetch_flavor(
+	client: &reqwest::Client,
+	config: &Config,
+	failures: &Failures,
+	flavor: &Flavor,
+) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join(flavor.name);
+	fs::create_dir_all(upstream_base.join("loader"))?;
+	fs::create_dir_all(upstream_base.join("artifacts"))?;
+
+	let intermediaries = fetch_versions(client, flavor, upstream_base, "intermediary").await?;
+	futures::stream::iter(intermediaries)
+		.map(anyhow::Ok)
+		.try_for_each_concurrent(config.concurrency, |intermediary| async move {
+			let url = intermediary.maven.to_url(flavor.maven_url);
+			let path = artifact_info_path(upstream_base, &intermediary.maven);
+			failures.check(
+				flavor.name,
+				&intermediary.maven,
+				fetch_artifact_info(client, &url, &path).await,
+			)?;
+			Ok(())
+		})
+		.await?;
+
+	if flavor.loader_id.is_none() {
+		return Ok(());
+	}
+	let loaders = fetch_versions(client, flavor, upstream_base, "loader").await?;
+	futures::stream::iter(loaders)
+		.map(Ok)
+		.try_for_each_concurrent(config.concurrency, |loader| async move {
+			failures.check(
+				flavor.name,
+				&loader.maven,
+				fetch_loader(client, config, flavor, upstream_base, &loader).await,
+			)?;
+			Ok(())
+		})
+		.await
+}
+
+async fn fetch_versions(
+	client: &reqwest::Client,
+	flavor: &Flavor,
+	upstream_base: &Path,
+	kind: &str,
+) -> Result<Vec<MetaVersion>> {
+	let url = format!("{}{kind}", flavor.meta_url);
+	let path = upstream_base.join(format!("{kind}.json"));
+	let content = with_retry(|| fetch_cached(client, &url, &path)).await?;
+	serde_json::from_slice(&content).with_context(|| format!("Failed to parse the {kind} list"))
+}
+
+/// Downloads the profile of a loader, and the artifact info of the loader and its libraries, as
+/// well as of their dev downloads if [Config::dev_downloads] is set.
+async fn fetch_loader(
+	client: &reqwest::Client,
+	config: &Config,
+	flavor: &Flavor,
+	upstream_base: &Path,
+	loader: &MetaVersion,
+) -> Result<()> {
+	let loader_url = loader.maven.to_url(flavor.maven_url);
+	fetch_artifact_info(
+		client,
+		&loader_url,
+		&artifact_info_path(upstream_base, &loader.maven),
+	)
+	.await?;
+	if config.dev_downloads {
+		fetch_dev_artifact_infos(client, upstream_base, &loader.maven, &loader_url).await?;
+	}
+
+	let profile_name = GradleSpecifier {
+		extension: "json".into(),
+		..loader.maven.clone()
+	};
+	let profile_path = upstream_base
+		.join("loader")
+		.join(format!("{}.json", loader.version));
+	// released profiles don't change
+	let profile = if profile_path.is_file() {
+		fs::read(&profile_path)?
+	} else {
+		let url = profile_name.to_url(flavor.maven_url);
+		let content = with_retry(|| async {
+			Ok(client
+				.get(&url)
+				.send()
+				.await?
+				.error_for_status()?
+				.bytes()
+				.await?)
+		})
+		.await?;
+		fs::write(&profile_path, &content)?;
+		content.into()
+	};
+	let profile: LoaderProfile = serde_json::from_slice(&profile)
+		.with_context(|| format!("Failed to parse {}", profile_path.display()))?;
+
+	for library in profile
+		.libraries
+		.common
+		.iter()
+		.chain(&profile.libraries.client)
+	{
+		let url = library.name.to_url(&library.url);
+		fetch_artifact_info(
+			client,
+			&url,
+			&artifact_info_path(upstream_base, &library.name),
+		)
+		.await?;
+		if config.dev_downloads {
+			fetch_dev_artifact_infos(client, upstream_base, &library.name, &url).await?;
+		}
+	}
+	Ok(())
+}
+
+pub fn process(config: &Config, failures: &Failures) -> Result<()> {
+	process_flavor(config, failures, &FABRIC)
+}
+
+/// Generates the intermediary and loader components of `flavor` from the fetched data.
+pub fn process_flavor(config: &Config, failures: &Failures, flavor: &Flavor) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join(flavor.name);
+
+	let intermediaries: Vec<MetaVersion> =
+		serde_json::from_slice(&fs::read(upstream_base.join("intermediary.json"))?)
+			.with_context(|| "Failed to parse the intermediary list")?;
+	let out_base = &config.paths.out_root.join(flavor.intermediary_id);
+	fs::create_dir_all(out_base)?;
+	let mut index: helix::index::Index = vec![];
+	for intermediary in intermediaries {
+		if let Some(component) = failures.check(
+			flavor.name,
+			intermediary.maven.to_string(),
+			process_intermediary(flavor, upstream_base, intermediary),
+		)? {
+			index.push(index::write_component(out_base, &component)?);
+		}
+	}
+	index::write_index(out_base, index)?;
+
+	let Some(loader_id) = flavor.loader_id else {
+		return Ok(());
+	};
+	let loaders: Vec<MetaVersion> =
+		serde_json::from_slice(&fs::read(upstream_base.join("loader.json"))?)
+			.with_context(|| "Failed to parse the loader list")?;
+	let out_base = &config.paths.out_root.join(loader_id);
+	fs::create_dir_all(out_base)?;
+	let mut index: helix::index::Index = vec![];
+	for loader in loaders {
+		if let Some(component) = failures.check(
+			flavor.name,
+			loader.maven.to_string(),
+			process_loader(config, flavor, loader_id, upstream_base, loader),
+		)? {
+			index.push(index::write_component(out_base, &component)?);
+		}
+	}
+	index::write_index(out_base, index)
+}
+
+/// The intermediary of a game version is versioned like the game, and released when it was
+/// published to the Maven repository.
+fn process_intermediary(
+	flavor: &Flavor,
+	upstream_base: &Path,
+	intermediary: MetaVersion,
+) -> Result<helix::component::Component> {
+	let (download, info) = artifact_download(
+		upstream_base,
+		&intermediary.maven,
+		intermediary.maven.to_url(flavor.maven_url),
+	)?;
+	let component = helix::component::Component {
+		dependencies: helix::component::Dependencies {
+			requires: vec![helix::component::ComponentDependency {
+				id: "net.minecraft".into(),
+				version: Some(intermediary.version.clone()),
+			}],
+			provides: flavor
+				.intermediary_provides
+				.iter()
+				.map(|id| helix::component::ComponentDependency {
+					id: (*id).into(),
+					version: Some(intermediary.version.clone()),
+				})
+				.collect(),
+			..Default::default()
+		},
+		downloads: vec![download],
+		classpath: vec![helix::component::ConditionalClasspathEntry::All(
+			intermediary.maven,
+		)],
+		..helix::component::Component::new(
+			flavor.intermediary_id,
+			intermediary.version,
+			info.last_modified,
+		)
+	};
+	component.validate()?;
+	Ok(component)
+}
+
+/// A loader works with any game version that has an intermediary, so neither is pinned.
+fn process_loader(
+	config: &Config,
+	flavor: &Flavor,
+	loader_id: &str,
+	upstream_base: &Path,
+	loader: MetaVersion,
+) -> Result<helix::component::Component> {
+	let profile_path = upstream_base
+		.join("loader")
+		.join(format!("{}.json", loader.version));
+	let profile: LoaderProfile = serde_json::from_slice(
+		&fs::read(&profile_path).with_context(|| "Profile was not fetched")?,
+	)
+	.with_context(|| format!("Failed to parse {}", profile_path.display()))?;
+
+	let (loader_download, info) = artifact_download(
+		upstream_base,
+		&loader.maven,
+		loader.maven.to_url(flavor.maven_url),
+	)?;
+	let mut downloads = vec![loader_download];
+	let mut classpath = vec![helix::component::ConditionalClasspathEntry::All(
+		loader.maven,
+	)];
+	for library in profile
+		.libraries
+		.common
+		.into_iter()
+		.chain(profile.libraries.client)
+	{
+		let url = library.name.to_url(&library.url);
+		downloads.push(artifact_download(upstream_base, &library.name, url)?.0);
+		classpath.push(helix::component::ConditionalClasspathEntry::All(
+			library.name,
+		));
+	}
+
+	let mut component = helix::component::Component {
+		dependencies: helix::component::Dependencies {
+			requires: ["net.minecraft", flavor.intermediary_id]
+				.into_iter()
+				.map(|id| helix::component::ComponentDependency {
+					id: id.into(),
+					version: None,
+				})
+				.collect(),
+			..Default::default()
+		},
+		downloads,
+		classpath,
+		main_class: Some(match profile.main_class {
+			LoaderMainClass::Sided { client } => client,
+			LoaderMainClass::Always(main_class) => main_class,
+		}),
+		min_java_version: profile.min_java_version,
+		..helix::component::Component::new(loader_id, loader.version, info.last_modified)
+	};
+	if config.dev_downloads {
+		add_dev_downloads(&mut component, upstream_base)?;
+	}
+	component.validate()?;
+	Ok(component)
+}
@@ -4,51 +4,173 @@
  * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{collections::BTreeSet, fs, path::Path};
+use std::{
+	borrow::Cow,
+	collections::BTreeMap,
+	fs,
+	io::{BufReader, Read, Seek},
+	path::Path,
+};
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use indexmap::{IndexMap, IndexSet};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use zip::ZipArchive;
 
 use helixlauncher_meta as helix;
+use helixlauncher_meta::util::GradleSpecifier;
 use lazy_static::lazy_static;
-use regex::Regex;
+use regex::{Captures, Regex};
 
-use crate::mojang;
+use crate::{
+	config::Config,
+	index,
+	mojang::{self, MojangConditionalValue, MojangLibrary},
+	report::Failures,
+};
 
-pub fn process() -> Result<()> {
-	let version_base = Path::new("upstream/forge/installers");
+/// A loader distributed with the Forge installer, which is Forge itself and its forks.
+pub struct Flavor {
+	/// The id of the generated components.
+	pub id: &'static str,
+	/// The directory under the upstream root that holds the installers, in `installers`.
+	pub upstream: &'static str,
+	/// The Maven group and artifact id of the loader, which the installer is a classifier of.
+	pub group: &'static str,
+	pub artifact: &'static str,
+	pub maven_url: &'static str,
+	/// Extracts the component version and the version of the loader artifact from the id of
+	/// `version.json` and the Minecraft version it inherits from.
+	pub versions: fn(id: &str, minecraft_version: &str) -> Result<(String, String)>,
+}
+
+pub const FORGE: Flavor = Flavor {
+	id: "net.minecraftforge.forge",
+	upstream: "forge",
+	group: "net.minecraftforge",
+	artifact: "forge",
+	maven_url: "https://maven.minecraftforge.net/",
+	versions: forge_versions,
+};
+
+/// The parts of `install_profile.json` needed for the processors of modern installers (1.13 and
+/// up). Unknown keys are allowed, as they differ between the spec versions of the installer and
+/// only matter to the installer itself. Older installers have none of these keys.
+#[derive(Deserialize, Debug)]
+struct InstallProfile {
+	#[serde(default)]
+	data: IndexMap<String, SidedValue>,
+	#[serde(default)]
+	processors: Vec<InstallProcessor>,
+	#[serde(default)]
+	libraries: Vec<MojangLibrary>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct SidedValue {
+	client: String,
+	#[serde(rename = "server")]
+	_server: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct InstallProcessor {
+	/// Missing means both sides.
+	sides: Option<Vec<String>>,
+	jar: GradleSpecifier,
+	#[serde(default)]
+	classpath: Vec<GradleSpecifier>,
+	#[serde(default)]
+	args: Vec<String>,
+	#[serde(default)]
+	outputs: BTreeMap<String, String>,
+}
+
+pub fn process(config: &Config, failures: &Failures) -> Result<()> {
+	process_installers(config, failures, &FORGE)
+}
+
+/// Processes every installer of `flavor` in the upstream directory.
+pub fn process_installers(config: &Config, failures: &Failures, flavor: &Flavor) -> Result<()> {
+	let version_base = &config
+		.paths
+		.upstream_root
+		.join(flavor.upstream)
+		.join("installers");
 	fs::create_dir_all(version_base)?;
-	let out_base = Path::new("out/net.minecraftforge.forge");
+	let out_base = &config.paths.out_root.join(flavor.id);
 	fs::create_dir_all(out_base)?;
 
 	let mut index: helix::index::Index = vec![];
 
 	for file in fs::read_dir(version_base)? {
 		let file = file?;
-		let component = process_version(&file, out_base)
-			.with_context(|| format!("Failed to process {}", file.file_name().to_str().unwrap()))?;
-		index.push(component.into());
+		let name = file.file_name().to_str().unwrap().to_owned();
+		// skips partial downloads
+		if !name.ends_with(".jar") {
+			continue;
+		}
+		if let Some(entry) = failures.check(
+			flavor.upstream,
+			name,
+			process_version(&file, out_base, flavor),
+		)? {
+			index.push(entry);
+		}
 	}
 
-	index.sort_by(|x, y| y.release_time.cmp(&x.release_time));
+	index::write_index(out_base, index)
+}
+
+fn process_version(
+	file: &fs::DirEntry,
+	out_base: &Path,
+	flavor: &Flavor,
+) -> Result<helix::index::IndexEntry> {
+	// FIXME: installers older than 1.12.2 have no version.json
+	let mut archive = ZipArchive::new(fs::File::open(file.path())?)?;
 
-	fs::write(
-		out_base.join("index.json"),
-		serde_json::to_string_pretty(&index)?,
-	)?;
+	let profile: InstallProfile =
+		serde_json::from_reader(BufReader::new(archive.by_name("install_profile.json")?))
+			.with_context(|| "Failed to parse install_profile.json")?;
+	let component = if profile.processors.is_empty() {
+		process_legacy(&mut archive, flavor)?
+	} else {
+		process_modern(&file.path(), &mut archive, profile, flavor)?
+	};
+	component.validate()?;
+	index::write_component(out_base, &component)
+}
 
-	Ok(())
+lazy_static! {
+	static ref VERSION_PATTERN: Regex =
+		Regex::new("^(?:[0-9.]+-forge-|[0-9.]+-Forge)(?P<forge_version>[0-9.]+)$").unwrap();
 }
 
-fn process_version(file: &fs::DirEntry, out_base: &Path) -> Result<helix::component::Component> {
-	// FIXME: this doesn't support like anything other than 1.12.2 and some more recent older versions
-	lazy_static! {
-		static ref VERSION_PATTERN: Regex =
-			Regex::new("^(?:[0-9.]+-forge-|[0-9.]+-Forge)(?P<forge_version>[0-9.]+)$").unwrap();
-	}
-	let mut archive = zip::ZipArchive::new(std::fs::File::open(file.path())?)?;
+/// Forge versions are named after the Minecraft version and the Forge version, e.g.
+/// `1.20.1-forge-47.1.0`; its artifacts are versioned `1.20.1-47.1.0`.
+fn forge_versions(id: &str, minecraft_version: &str) -> Result<(String, String)> {
+	let m = VERSION_PATTERN
+		.captures(id)
+		.with_context(|| format!("Could not extract Forge version from {id}"))?;
+	let forge_version = m.name("forge_version").unwrap().as_str();
+	Ok((
+		forge_version.to_owned(),
+		format!("{minecraft_version}-{forge_version}"),
+	))
+}
 
-	let file = std::io::BufReader::new(archive.by_name("version.json")?);
-	let version: mojang::MojangVersion = serde_json::from_reader(file)?;
+/// Processes an installer that only adds libraries and a tweaker to the game, as done up to
+/// 1.12.2.
+fn process_legacy(
+	archive: &mut ZipArchive<impl Read + Seek>,
+	flavor: &Flavor,
+) -> Result<helix::component::Component> {
+	let file = BufReader::new(archive.by_name("version.json")?);
+	let version = mojang::MojangVersion::from_reader(file)?;
 	ensure!(version.downloads.is_none());
 	ensure!(version.asset_index.is_none());
 	ensure!(version.arguments.is_none());
@@ -58,10 +180,7 @@ fn process_version(file: &fs::DirEntry, out_base: &Path) -> Result<helix::compon
 	let minecraft_version = version
 		.inherits_from
 		.with_context(|| "Minecraft version missing")?;
-	let m = VERSION_PATTERN
-		.captures(&version.id)
-		.with_context(|| format!("Could not extract Forge version from {}", version.id))?;
-	let forge_version = m.name("forge_version").unwrap().as_str();
+	let (forge_version, _) = (flavor.versions)(&version.id, &minecraft_version)?;
 	let mut downloads = Vec::with_capacity(version.libraries.len());
 	let mut classpath = Vec::with_capacity(version.libraries.len());
 	for library in version.libraries {
@@ -76,7 +195,7 @@ fn process_version(file: &fs::DirEntry, out_base: &Path) -> Result<helix::compon
 			name: library.name.clone(),
 			url: artifact.url,
 			size: artifact.size,
-			hash: helix::component::Hash::SHA1(artifact.sha1),
+			hash: helix::component::Hash::parse_sha1(&artifact.sha1)?,
 		});
 		classpath.push(helix::component::ConditionalClasspathEntry::All(
 			library.name,
@@ -86,32 +205,236 @@ fn process_version(file: &fs::DirEntry, out_base: &Path) -> Result<helix::compon
 		.find("--tweakClass")
 		.with_context(|| "Invalid Minecraft arguments")?..];
 	ensure!(!args.contains('$'));
-	let component = helix::component::Component {
-		format_version: 1,
-		id: "net.minecraftforge.forge".into(),
-		version: forge_version.into(),
+	let mut tweakers = vec![];
+	let mut game_arguments = vec![];
+	let mut args = args.split(' ');
+	while let Some(arg) = args.next() {
+		if arg == "--tweakClass" {
+			tweakers.push(
+				args.next()
+					.with_context(|| "--tweakClass without a class")?
+					.to_owned(),
+			);
+		} else {
+			game_arguments.push(helix::component::MinecraftArgument::Always(arg.into()));
+		}
+	}
+	Ok(helix::component::Component {
+		dependencies: requires_minecraft(minecraft_version),
+		downloads,
+		main_class: Some(version.main_class),
+		game_arguments,
+		tweakers,
+		classpath,
+		..helix::component::Component::new(flavor.id, forge_version, version.release_time)
+	})
+}
+
+/// LaunchWrapper, which runs the tweakers of legacy Forge and of loaders built for it, in the
+/// build used by Forge for 1.12.2.
+pub fn launchwrapper() -> Result<helix::component::Download> {
+	Ok(helix::component::Download {
+		name: "net.minecraft:launchwrapper:1.12".parse()?,
+		url: "https://libraries.minecraft.net/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar"
+			.into(),
+		size: 32999,
+		hash: helix::component::Hash::parse_sha1("111e7bea9c968cdb3d06ef4632bf7ff0824d0f36")?,
+	})
+}
+
+pub fn requires_minecraft(minecraft_version: String) -> helix::component::Dependencies {
+	helix::component::Dependencies {
 		requires: vec![helix::component::ComponentDependency {
 			id: "net.minecraft".into(),
 			version: Some(minecraft_version),
 		}],
-		traits: BTreeSet::new(),
-		assets: None,
-		conflicts: vec![],
-		downloads,
-		jarmods: vec![],
-		game_jar: None,
+		..Default::default()
+	}
+}
+
+/// Processes an installer that patches the game jar with processors, as done since 1.13. The
+/// processors end up in [helix::component::Installer], for the launcher to run.
+fn process_modern(
+	path: &Path,
+	archive: &mut ZipArchive<impl Read + Seek>,
+	profile: InstallProfile,
+	flavor: &Flavor,
+) -> Result<helix::component::Component> {
+	let version =
+		mojang::MojangVersion::from_reader(BufReader::new(archive.by_name("version.json")?))
+			.with_context(|| "Failed to parse version.json")?;
+	ensure!(version.downloads.is_none());
+	ensure!(version.asset_index.is_none());
+	ensure!(version.minecraft_arguments.is_none());
+	let minecraft_version = version
+		.inherits_from
+		.clone()
+		.with_context(|| "Minecraft version missing")?;
+	let (forge_version, artifact_version) = (flavor.versions)(&version.id, &minecraft_version)?;
+
+	let installer_name = GradleSpecifier {
+		group: flavor.group.into(),
+		artifact: flavor.artifact.into(),
+		version: artifact_version,
+		classifier: Some("installer".into()),
+		extension: "jar".into(),
+	};
+	let installer = fs::read(path)?;
+	let mut downloads = IndexMap::new();
+	downloads.insert(
+		installer_name.clone(),
+		helix::component::Download {
+			url: installer_name.to_url(flavor.maven_url),
+			name: installer_name.clone(),
+			size: installer.len().try_into()?,
+			hash: helix::component::Hash::SHA1(Sha1::digest(&installer).into()),
+		},
+	);
+
+	let mut bundled = IndexSet::new();
+	let mut generated = IndexSet::new();
+	// libraries without a URL are either in the installer or written by the processors
+	let mut add_library = |library: &MojangLibrary, may_be_generated: bool| -> Result<()> {
+		ensure!(library.rules.is_empty());
+		ensure!(library.natives.is_empty());
+		ensure!(library.downloads.classifiers.is_empty());
+		let artifact = library
+			.downloads
+			.artifact
+			.as_ref()
+			.with_context(|| format!("Artifact for {} missing", library.name))?;
+		if artifact.url.is_empty() {
+			if archive
+				.index_for_name(&format!("maven/{}", artifact.path))
+				.is_some()
+			{
+				bundled.insert(library.name.clone());
+			} else {
+				ensure!(
+					may_be_generated,
+					"{} has no URL and is not in the installer",
+					library.name
+				);
+				generated.insert(library.name.clone());
+			}
+			return Ok(());
+		}
+		let hash = helix::component::Hash::parse_sha1(&artifact.sha1)?;
+		if let Some(download) = downloads.get(&library.name) {
+			ensure!(
+				download.hash == hash,
+				"{} has two different hashes",
+				library.name
+			);
+		} else {
+			downloads.insert(
+				library.name.clone(),
+				helix::component::Download {
+					name: library.name.clone(),
+					url: artifact.url.clone(),
+					size: artifact.size,
+					hash,
+				},
+			);
+		}
+		Ok(())
+	};
+
+	// the libraries of the install profile are only needed by the processors
+	for library in &profile.libraries {
+		add_library(library, false)?;
+	}
+	let mut classpath = Vec::with_capacity(version.libraries.len());
+	for library in &version.libraries {
+		add_library(library, true)?;
+		classpath.push(helix::component::ConditionalClasspathEntry::All(
+			library.name.clone(),
+		));
+	}
+
+	let processors = profile
+		.processors
+		.into_iter()
+		.filter(|processor| {
+			processor
+				.sides
+				.as_ref()
+				.is_none_or(|sides| sides.iter().any(|side| side == "client"))
+		})
+		.map(|processor| helix::component::Processor {
+			jar: processor.jar,
+			classpath: processor.classpath,
+			args: processor.args,
+			outputs: processor.outputs,
+		})
+		.collect();
+
+	let (game_arguments, jvm_arguments) = match &version.arguments {
+		Some(arguments) => (
+			convert_arguments(&arguments.game)?,
+			convert_arguments(&arguments.jvm)?,
+		),
+		None => (vec![], vec![]),
+	};
+
+	Ok(helix::component::Component {
+		dependencies: requires_minecraft(minecraft_version),
+		downloads: downloads.into_values().collect(),
 		main_class: Some(version.main_class),
-		game_arguments: args
-			.split(' ')
-			.map(|s| helix::component::MinecraftArgument::Always(s.into()))
-			.collect(),
+		game_arguments,
+		jvm_arguments,
 		classpath,
-		natives: vec![],
-		release_time: version.release_time,
-	};
-	fs::write(
-		out_base.join(format!("{}.json", component.version)),
-		serde_json::to_string_pretty(&component)?,
-	)?;
-	Ok(component)
+		installer: Some(helix::component::Installer {
+			installer: installer_name,
+			data: profile
+				.data
+				.into_iter()
+				.map(|(key, value)| (key, value.client))
+				.collect(),
+			processors,
+			bundled: bundled.into_iter().collect(),
+			generated: generated.into_iter().collect(),
+		}),
+		..helix::component::Component::new(flavor.id, forge_version, version.release_time)
+	})
+}
+
+fn convert_arguments(
+	arguments: &[MojangConditionalValue<String>],
+) -> Result<Vec<helix::component::MinecraftArgument>> {
+	arguments
+		.iter()
+		.map(|argument| match argument {
+			MojangConditionalValue::Always(argument) => Ok(
+				helix::component::MinecraftArgument::Always(remap_vars(argument)?.into()),
+			),
+			MojangConditionalValue::Conditional { .. } => {
+				bail!("Conditional Forge arguments are not supported")
+			}
+		})
+		.collect()
+}
+
+/// Maps the placeholders Forge uses in its arguments, which are a different set from those of
+/// the game.
+fn remap_vars(s: &str) -> Result<Cow<'_, str>> {
+	lazy_static! {
+		static ref VAR_PATTERN: Regex = Regex::new("(\\$\\{[a-zA-Z0-9_]+\\})").unwrap();
+	}
+	if let Some(unknown) = VAR_PATTERN.find_iter(s).find(|m| {
+		!matches!(
+			m.as_str(),
+			"${library_directory}" | "${classpath_separator}" | "${version_name}"
+		)
+	}) {
+		bail!("{} not supported in {s}", unknown.as_str());
+	}
+	Ok(
+		VAR_PATTERN.replace_all(s, |c: &Captures<'_>| match c.get(1).unwrap().as_str() {
+			"${library_directory}" => "${launcher.libraries_dir}",
+			"${classpath_separator}" => "${launch.classpath_separator}",
+			"${version_name}" => "${instance.minecraft_version}",
+			_ => unreachable!(),
+		}),
+	)
 }
@@ -0,0 +1,261 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+	cmp::Reverse,
+	env, fs,
+	path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use helixlauncher_meta as helix;
+
+use crate::{write_if_changed, write_json};
+
+/// Writes `component` to `out_base`, and returns its index entry, hashing the exact bytes
+/// written.
+pub fn write_component(
+	out_base: &Path,
+	component: &helix::component::Component,
+) -> Result<helix::index::IndexEntry> {
+	let content = serde_json::to_vec_pretty(component)?;
+	write_if_changed(
+		&out_base.join(format!("{}.json", component.version)),
+		&content,
+	)?;
+	Ok(helix::index::IndexEntry::new(
+		component,
+		Sha256::digest(&content).into(),
+	))
+}
+
+pub fn load_component(path: &Path) -> Result<helix::component::Component> {
+	let value = serde_json::from_str(&fs::read_to_string(path)?)
+		.with_context(|| format!("Failed to parse {}", path.display()))?;
+	helix::component::upgrade(value).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn write_index(out_base: &Path, mut index: helix::index::Index) -> Result<()> {
+	// versions released at the same time are sorted by version, as the entries may come from
+	// directory iteration, whose order changes between runs
+	index.sort_by_key(|entry| (Reverse(entry.release_time), entry.version.clone()));
+
+	let path = out_base.join("index.json");
+	write_json(&path, &index)?;
+	sign(&path)
+}
+
+/// Loads the ed25519 signing key, given as 32 hex-encoded bytes either directly in
+/// `HELIX_META_SIGNING_KEY` or in the file named by `HELIX_META_SIGNING_KEY_FILE`.
+fn signing_key() -> Result<Option<SigningKey>> {
+	let key = match (
+		env::var("HELIX_META_SIGNING_KEY"),
+		env::var_os("HELIX_META_SIGNING_KEY_FILE"),
+	) {
+		(Ok(key), _) => key,
+		(Err(_), Some(path)) => fs::read_to_string(&path).with_context(|| {
+			format!("Failed to read signing key {}", Path::new(&path).display())
+		})?,
+		(Err(_), None) => return Ok(None),
+	};
+	let key = HEXLOWER_PERMISSIVE
+		.decode(key.trim().as_bytes())
+		.with_context(|| "Signing key is not valid hex")?;
+	let key = key
+		.try_into()
+		.map_err(|_| anyhow!("Signing key must be 32 bytes"))?;
+	Ok(Some(SigningKey::from_bytes(&key)))
+}
+
+/// Writes a detached signature of the file at `path` to `<path>.sig` as hex, if a signing key is
+/// configured.
+fn sign(path: &Path) -> Result<()> {
+	let Some(key) = signing_key()? else {
+		return Ok(());
+	};
+	let signature = key.sign(&fs::read(path)?);
+	let mut signature_path = path.as_os_str().to_owned();
+	signature_path.push(".sig");
+	write_if_changed(
+		Path::new(&signature_path),
+		HEXLOWER.encode(&signature.to_bytes()).as_bytes(),
+	)
+}
+
+/// The directory under the output the JSON Schemas are written to, which isn't a component.
+pub const SCHEMA_DIR: &str = "schema";
+
+/// Lists the component directories under `out_base`.
+pub fn component_dirs(out_base: &Path) -> Result<Vec<PathBuf>> {
+	let mut dirs = vec![];
+	for dir in fs::read_dir(out_base)? {
+		let dir = dir?;
+		if dir.file_type()?.is_dir() && dir.file_name() != SCHEMA_DIR {
+			dirs.push(dir.path());
+		}
+	}
+	Ok(dirs)
+}
+
+/// Loads every component written to `dir`, skipping its index.
+pub fn load_components(dir: &Path) -> Result<Vec<helix::component::Component>> {
+	let mut components = vec![];
+	for file in fs::read_dir(dir)? {
+		let path = file?.path();
+		if path.extension().is_none_or(|extension| extension != "json")
+			|| path.file_name().unwrap() == "index.json"
+		{
+			continue;
+		}
+		components.push(load_component(&path)?);
+	}
+	Ok(components)
+}
+
+/// Builds the index entries of every component written to `dir` from the files, as they are on
+/// disk.
+pub fn load_entries(dir: &Path) -> Result<helix::index::Index> {
+	let mut index = vec![];
+	for file in fs::read_dir(dir)? {
+		let path = file?.path();
+		if path.extension().is_none_or(|extension| extension != "json")
+			|| path.file_name().unwrap() == "index.json"
+		{
+			continue;
+		}
+		let content = fs::read(&path)?;
+		let value = serde_json::from_slice(&content)
+			.with_context(|| format!("Failed to parse {}", path.display()))?;
+		let component = helix::component::upgrade(value)
+			.with_context(|| format!("Failed to parse {}", path.display()))?;
+		index.push(helix::index::IndexEntry::new(
+			&component,
+			Sha256::digest(&content).into(),
+		));
+	}
+	Ok(index)
+}
+
+/// The part of an index entry that [reindex] keeps. Nothing else is read, so that indexes in the
+/// format of an older generator can be rebuilt.
+#[derive(Deserialize)]
+struct PreviousEntry {
+	version: String,
+	#[serde(default)]
+	latest: Vec<String>,
+}
+
+/// Rebuilds every `index.json` under `out_base` from the components already written there, without
+/// touching upstream data.
+pub fn reindex(out_base: &Path) -> Result<()> {
+	for dir in component_dirs(out_base)? {
+		let mut index = load_entries(&dir)?;
+		// which versions are the latest isn't recorded in the components, so it is kept from the
+		// previous index
+		let path = dir.join("index.json");
+		if path.is_file() {
+			let previous: Vec<PreviousEntry> = serde_json::from_str(&fs::read_to_string(&path)?)
+				.with_context(|| format!("Failed to parse {}", path.display()))?;
+			for previous in previous {
+				if let Some(entry) = index
+					.iter_mut()
+					.find(|entry| entry.version == previous.version)
+				{
+					entry.latest = previous.latest;
+				}
+			}
+		}
+		write_index(&dir, index)?;
+	}
+
+	Ok(())
+}
+
+/// Writes `search.json` to `out_base`, a flat list of every version of every component, from the
+/// per-component indexes.
+pub fn write_search_index(out_base: &Path) -> Result<()> {
+	let mut dirs = component_dirs(out_base)?;
+	dirs.sort();
+
+	let mut search: helix::index::SearchIndex = vec![];
+	for dir in dirs {
+		let path = dir.join("index.json");
+		let index: helix::index::Index = serde_json::from_str(&fs::read_to_string(&path)?)
+			.with_context(|| format!("Failed to parse {}", path.display()))?;
+		let id = dir.file_name().unwrap().to_str().unwrap();
+		search.extend(index.into_iter().map(|entry| helix::index::SearchEntry {
+			id: id.to_owned(),
+			entry,
+		}));
+	}
+
+	write_json(&out_base.join("search.json"), &search)
+}
+
+/// Writes the top-level `index.json` to `out_base`, listing every component that has an index,
+/// sorted by id, along with its latest version.
+pub fn write_component_index(out_base: &Path) -> Result<()> {
+	let mut dirs = component_dirs(out_base)?;
+	dirs.sort();
+
+	let mut components: helix::index::ComponentIndex = vec![];
+	for dir in dirs {
+		let path = dir.join("index.json");
+		if !path.is_file() {
+			continue;
+		}
+		let index: helix::index::Index = serde_json::from_str(&fs::read_to_string(&path)?)
+			.with_context(|| format!("Failed to parse {}", path.display()))?;
+		let Some(latest) = index.into_iter().max_by_key(|entry| entry.release_time) else {
+			continue;
+		};
+		let id = dir.file_name().unwrap().to_str().unwrap();
+		components.push(helix::index::ComponentSummary {
+			id: id.to_owned(),
+			index: format!("{id}/index.json"),
+			latest_version: latest.version,
+			latest_release_time: latest.release_time,
+		});
+	}
+
+	let path = out_base.join("index.json");
+	write_json(&path, &components)?;
+	sign(&path)
+}
+
+/// Checks that every dependency declared by a component in `out_base` points at a component that
+/// was actually generated.
+pub fn check_requires(out_base: &Path) -> Result<()> {
+	let mut missing = vec![];
+
+	for dir in component_dirs(out_base)? {
+		for component in load_components(&dir)? {
+			for dependency in &component.dependencies.requires {
+				let target = out_base.join(&dependency.id);
+				let exists = match &dependency.version {
+					Some(version) => target.join(format!("{version}.json")).is_file(),
+					None => target.join("index.json").is_file(),
+				};
+				if !exists {
+					missing.push(format!(
+						"{} {} requires {dependency}",
+						component.id, component.version
+					));
+				}
+			}
+		}
+	}
+
+	if !missing.is_empty() {
+		bail!("Unsatisfied dependencies:\n{}", missing.join("\n"));
+	}
+	Ok(())
+}
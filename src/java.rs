@@ -0,0 +1,250 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use data_encoding::HEXLOWER;
+use futures::{StreamExt, TryStreamExt};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+use helixlauncher_meta as helix;
+use helixlauncher_meta::component::{Arch, OsName};
+
+use crate::{config::Config, fetch_cached, index, report::Failures, with_retry};
+
+const ALL_RUNTIMES_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+#[derive(Deserialize, Debug)]
+struct RuntimeManifest {
+	sha1: String,
+	size: u32,
+	url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RuntimeVersion {
+	name: String,
+	released: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Runtime {
+	manifest: RuntimeManifest,
+	version: RuntimeVersion,
+}
+
+/// platform -> runtime name -> builds (in practice at most one)
+type AllRuntimes = IndexMap<String, IndexMap<String, Vec<Runtime>>>;
+
+/// Picks the build of a runtime to use, should Mojang ever list more than one for a platform.
+fn newest(builds: Vec<Runtime>) -> Option<Runtime> {
+	builds
+		.into_iter()
+		.max_by_key(|runtime| runtime.version.released)
+}
+
+/// Maps Mojang's platform names to ours. `None` means the platform is not one a launcher can run
+/// on (`gamecore` is the Xbox build).
+fn platform(name: &str) -> Result<Option<helix::component::Platform>> {
+	let (os, arch) = match name {
+		"gamecore" => return Ok(None),
+		"linux" => (OsName::Linux, Arch::X86_64),
+		"linux-i386" => (OsName::Linux, Arch::X86),
+		"mac-os" => (OsName::Osx, Arch::X86_64),
+		"mac-os-arm64" => (OsName::Osx, Arch::Arm64),
+		"windows-x64" => (OsName::Windows, Arch::X86_64),
+		"windows-x86" => (OsName::Windows, Arch::X86),
+		"windows-arm64" => (OsName::Windows, Arch::Arm64),
+		_ => bail!("Unknown Java runtime platform {name}"),
+	};
+	Ok(Some(helix::component::Platform {
+		os: vec![os],
+		arch: Some(arch),
+		os_version: None,
+	}))
+}
+
+pub async fn fetch(client: &reqwest::Client, config: &Config, failures: &Failures) -> Result<()> {
+	let runtime_base = config.paths.upstream_root.join("mojang/java-runtime");
+	let manifest_base = runtime_base.join("manifests");
+	fs::create_dir_all(&manifest_base)?;
+
+	let all_path = runtime_base.join("all.json");
+	let content = with_retry(|| fetch_cached(client, ALL_RUNTIMES_URL, &all_path)).await?;
+	let runtimes: AllRuntimes = serde_json::from_slice(&content)?;
+
+	let manifest_base = &manifest_base;
+	futures::stream::iter(
+		runtimes
+			.into_values()
+			.flat_map(IndexMap::into_values)
+			.filter_map(newest),
+	)
+	.map(Ok)
+	.try_for_each_concurrent(config.concurrency, |runtime| async move {
+		let item = format!("{} ({})", runtime.version.name, runtime.manifest.sha1);
+		failures.check(
+			"java",
+			item,
+			fetch_manifest(client, manifest_base, runtime.manifest).await,
+		)?;
+		Ok(())
+	})
+	.await
+}
+
+/// Downloads a runtime file manifest to `<sha1>.json`, verifying its hash.
+async fn fetch_manifest(
+	client: &reqwest::Client,
+	manifest_base: &Path,
+	manifest: RuntimeManifest,
+) -> Result<()> {
+	let manifest_path = manifest_base.join(format!("{}.json", manifest.sha1));
+
+	if manifest_path.try_exists()? {
+		let content = fs::read(&manifest_path)?;
+		if HEXLOWER.encode(&Sha1::digest(content)) == manifest.sha1 {
+			return Ok(());
+		}
+	}
+	let content = with_retry(|| async {
+		Ok(client
+			.get(&manifest.url)
+			.send()
+			.await?
+			.error_for_status()?
+			.bytes()
+			.await?)
+	})
+	.await?;
+	if HEXLOWER.encode(&Sha1::digest(&content)) != manifest.sha1 {
+		bail!("{} has wrong SHA-1!", manifest.url)
+	}
+	fs::write(manifest_path, content)?;
+
+	Ok(())
+}
+
+/// Checks that the file manifest of `runtime` was fetched completely.
+fn check_manifest(runtime_base: &Path, runtime: &Runtime) -> Result<()> {
+	let manifest_path = runtime_base
+		.join("manifests")
+		.join(format!("{}.json", runtime.manifest.sha1));
+	ensure!(
+		fs::metadata(&manifest_path)
+			.with_context(|| "Manifest was not fetched")?
+			.len() == u64::from(runtime.manifest.size),
+		"Manifest has the wrong size"
+	);
+	Ok(())
+}
+
+pub fn process(config: &Config, failures: &Failures) -> Result<()> {
+	let runtime_base = &config.paths.upstream_root.join("mojang/java-runtime");
+	let out_base = &config.paths.out_root.join("com.mojang.java-runtime");
+	fs::create_dir_all(out_base)?;
+
+	let runtimes: AllRuntimes =
+		serde_json::from_str(&fs::read_to_string(runtime_base.join("all.json"))?)
+			.with_context(|| "Failed to parse all.json")?;
+
+	// runtime name -> builds for every platform
+	let mut components: IndexMap<String, Vec<(helix::component::Platform, Runtime)>> =
+		IndexMap::new();
+	for (platform_name, platform_runtimes) in runtimes {
+		let Some(platform) = platform(&platform_name)? else {
+			continue;
+		};
+		for (name, builds) in platform_runtimes {
+			if let Some(runtime) = newest(builds) {
+				let item = format!("{name} for {platform_name}");
+				if failures
+					.check("java", item, check_manifest(runtime_base, &runtime))?
+					.is_none()
+				{
+					continue;
+				}
+				components
+					.entry(name)
+					.or_default()
+					.push((platform.clone(), runtime));
+			}
+		}
+	}
+
+	let mut index: helix::index::Index = vec![];
+
+	for (name, builds) in components {
+		let release_time = builds
+			.iter()
+			.map(|(_, runtime)| runtime.version.released)
+			.max()
+			.unwrap();
+		let component = helix::component::Component {
+			java_runtimes: builds
+				.into_iter()
+				.map(|(platform, runtime)| helix::component::JavaRuntime {
+					platform,
+					version: runtime.version.name,
+					url: runtime.manifest.url,
+					sha1: runtime.manifest.sha1,
+					size: runtime.manifest.size,
+				})
+				.collect(),
+			..helix::component::Component::new("com.mojang.java-runtime", name, release_time)
+		};
+		component.validate()?;
+		index.push(index::write_component(out_base, &component)?);
+	}
+
+	index::write_index(out_base, index)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn build(name: &str, released: &str) -> serde_json::Value {
+		serde_json::json!({
+			"availability": { "group": 1, "progress": 100 },
+			"manifest": {
+				"sha1": "0000000000000000000000000000000000000000",
+				"size": 1,
+				"url": "https://example.com/manifest.json",
+				"mirror": "https://mirror.example.com/manifest.json",
+			},
+			"version": { "name": name, "released": released },
+		})
+	}
+
+	#[test]
+	fn newest_build_is_picked() {
+		let mut runtimes: AllRuntimes = serde_json::from_value(serde_json::json!({
+			"linux": {
+				"java-runtime-gamma": [
+					build("17.0.3", "2022-05-01T00:00:00+00:00"),
+					build("17.0.8", "2023-08-01T00:00:00+00:00"),
+					build("17.0.1", "2022-01-01T00:00:00+00:00"),
+				],
+				"jre-legacy": [],
+			},
+		}))
+		.unwrap();
+		let linux = runtimes.get_mut("linux").unwrap();
+		assert_eq!(
+			newest(linux.swap_remove("java-runtime-gamma").unwrap())
+				.unwrap()
+				.version
+				.name,
+			"17.0.8"
+		);
+		assert!(newest(linux.swap_remove("jre-legacy").unwrap()).is_none());
+	}
+}
@@ -0,0 +1,33 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use anyhow::Result;
+
+use crate::{
+	config::Config,
+	fabric::{self, Flavor},
+	report::Failures,
+};
+
+/// LegacyFabric publishes intermediaries for the game versions Fabric doesn't support (1.13.2 and
+/// older). They are run with the regular Fabric loader, which only asks for
+/// `net.fabricmc.intermediary`, so they provide that.
+pub const LEGACY_FABRIC: Flavor = Flavor {
+	name: "legacyfabric",
+	meta_url: "https://meta.legacyfabric.net/v2/versions/",
+	maven_url: "https://repo.legacyfabric.net/repository/legacyfabric/",
+	intermediary_id: "net.legacyfabric.intermediary",
+	intermediary_provides: &["net.fabricmc.intermediary"],
+	loader_id: None,
+};
+
+pub async fn fetch(client: &reqwest::Client, config: &Config, failures: &Failures) -> Result<()> {
+	fabric::fetch_flavor(client, config, failures, &LEGACY_FABRIC).await
+}
+
+pub fn process(config: &Config, failures: &Failures) -> Result<()> {
+	fabric::process_flavor(config, failures, &LEGACY_FABRIC)
+}
@@ -0,0 +1,218 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use futures::{StreamExt, TryStreamExt};
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use helixlauncher_meta as helix;
+use helixlauncher_meta::util::GradleSpecifier;
+
+use crate::{
+	artifact_download, artifact_info_path, config::Config, fetch_artifact_info, fetch_cached,
+	forge, index, report::Failures, with_retry,
+};
+
+const ID: &str = "com.mumfrey.liteloader";
+const VERSIONS_URL: &str = "https://dl.liteloader.com/versions/versions.json";
+/// Where libraries without a repository of their own are.
+const LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
+
+#[derive(Deserialize, Debug)]
+struct LiteLoaderVersions {
+	/// Minecraft version -> builds for it
+	versions: IndexMap<String, MinecraftVersion>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MinecraftVersion {
+	repo: Option<Repository>,
+	/// artifact -> build id -> build. Snapshot builds, listed under `snapshots`, are skipped, as
+	/// they are replaced under the same version.
+	#[serde(default)]
+	artefacts: IndexMap<String, IndexMap<String, Artefact>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Repository {
+	url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Artefact {
+	tweak_class: String,
+	#[serde(default)]
+	libraries: Vec<Library>,
+	version: String,
+	/// Seconds since the epoch, as a string.
+	timestamp: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Library {
+	name: GradleSpecifier,
+	/// The base URL of the Maven repository the library is on, [LIBRARIES_URL] if missing.
+	url: Option<String>,
+}
+
+impl Library {
+	fn url(&self) -> String {
+		self.name
+			.to_url(self.url.as_deref().unwrap_or(LIBRARIES_URL))
+	}
+}
+
+/// Every release build, with the Minecraft version and the repository it is in. The `latest`
+/// entries duplicate one of the others.
+fn builds(versions: LiteLoaderVersions) -> Vec<(String, String, Artefact)> {
+	let mut builds = vec![];
+	for (minecraft_version, version) in versions.versions {
+		let Some(repo) = version.repo else {
+			continue;
+		};
+		for (_, artefact) in version
+			.artefacts
+			.into_values()
+			.flatten()
+			.filter(|(id, _)| id != "latest")
+		{
+			builds.push((minecraft_version.clone(), repo.url.clone(), artefact));
+		}
+	}
+	builds
+}
+
+fn liteloader(version: &str) -> GradleSpecifier {
+	GradleSpecifier {
+		group: "com.mumfrey".into(),
+		artifact: "liteloader".into(),
+		version: version.into(),
+		classifier: None,
+		extension: "jar".into(),
+	}
+}
+
+/// Downloads the version list, and looks up the hashes and sizes of every release and its
+/// libraries, which the list doesn't have.
+pub async fn fetch(client: &reqwest::Client, config: &Config, failures: &Failures) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join("liteloader");
+	fs::create_dir_all(upstream_base.join("artifacts"))?;
+
+	let versions_path = upstream_base.join("versions.json");
+	let content = with_retry(|| fetch_cached(client, VERSIONS_URL, &versions_path)).await?;
+	let versions: LiteLoaderVersions =
+		serde_json::from_slice(&content).with_context(|| "Failed to parse versions.json")?;
+
+	futures::stream::iter(builds(versions))
+		.map(Ok)
+		.try_for_each_concurrent(config.concurrency, |(_, repo, artefact)| async move {
+			let version = artefact.version.clone();
+			failures.check(
+				"liteloader",
+				version,
+				fetch_build(client, upstream_base, &repo, &artefact).await,
+			)?;
+			Ok(())
+		})
+		.await
+}
+
+async fn fetch_build(
+	client: &reqwest::Client,
+	upstream_base: &Path,
+	repo: &str,
+	artefact: &Artefact,
+) -> Result<()> {
+	let name = liteloader(&artefact.version);
+	fetch_artifact_info(
+		client,
+		&name.to_url(repo),
+		&artifact_info_path(upstream_base, &name),
+	)
+	.await?;
+	for library in &artefact.libraries {
+		fetch_artifact_info(
+			client,
+			&library.url(),
+			&artifact_info_path(upstream_base, &library.name),
+		)
+		.await?;
+	}
+	Ok(())
+}
+
+pub fn process(config: &Config, failures: &Failures) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join("liteloader");
+	let out_base = &config.paths.out_root.join(ID);
+	fs::create_dir_all(out_base)?;
+
+	let versions: LiteLoaderVersions =
+		serde_json::from_slice(&fs::read(upstream_base.join("versions.json"))?)
+			.with_context(|| "Failed to parse versions.json")?;
+
+	let mut index: helix::index::Index = vec![];
+	for (minecraft_version, repo, artefact) in builds(versions) {
+		let version = artefact.version.clone();
+		if let Some(component) = failures.check(
+			"liteloader",
+			version,
+			process_build(upstream_base, minecraft_version, &repo, artefact),
+		)? {
+			index.push(index::write_component(out_base, &component)?);
+		}
+	}
+
+	index::write_index(out_base, index)
+}
+
+/// LiteLoader runs as a LaunchWrapper tweaker, like Forge up to 1.12.2.
+fn process_build(
+	upstream_base: &Path,
+	minecraft_version: String,
+	repo: &str,
+	artefact: Artefact,
+) -> Result<helix::component::Component> {
+	let name = liteloader(&artefact.version);
+	let release_time = DateTime::from_timestamp(
+		artefact
+			.timestamp
+			.parse()
+			.with_context(|| format!("Invalid timestamp {}", artefact.timestamp))?,
+		0,
+	)
+	.with_context(|| format!("Invalid timestamp {}", artefact.timestamp))?;
+
+	let mut downloads = vec![artifact_download(upstream_base, &name, name.to_url(repo))?.0];
+	for library in &artefact.libraries {
+		downloads.push(artifact_download(upstream_base, &library.name, library.url())?.0);
+	}
+	// older builds expect the launcher to provide LaunchWrapper
+	if !downloads.iter().any(|download| {
+		download.name.group == "net.minecraft" && download.name.artifact == "launchwrapper"
+	}) {
+		downloads.push(forge::launchwrapper()?);
+	}
+	let classpath = downloads
+		.iter()
+		.map(|download| helix::component::ConditionalClasspathEntry::All(download.name.clone()))
+		.collect();
+
+	let component = helix::component::Component {
+		dependencies: forge::requires_minecraft(minecraft_version),
+		downloads,
+		classpath,
+		main_class: Some("net.minecraft.launchwrapper.Launch".into()),
+		tweakers: vec![artefact.tweak_class],
+		..helix::component::Component::new(ID, artefact.version, release_time)
+	};
+	component.validate()?;
+	Ok(component)
+}
@@ -5,18 +5,824 @@
  */
 #![deny(rust_2018_idioms)]
 
-use anyhow::Result;
+use std::{
+	fs,
+	future::Future,
+	io, panic,
+	path::{Path, PathBuf},
+	thread,
+	time::Duration,
+};
 
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use data_encoding::HEXLOWER;
+use reqwest::{
+	header::{HeaderName, CONTENT_LENGTH, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+	StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use helixlauncher_meta as helix;
+use helixlauncher_meta::util::GradleSpecifier;
+
+mod config;
+mod fabric;
 mod forge;
+mod index;
+mod java;
+mod legacyfabric;
+mod liteloader;
 mod mojang;
+mod neoforge;
+mod optifine;
+mod packwiz;
+mod prism;
+mod report;
+mod validate;
+
+#[derive(Parser, Debug)]
+#[command(about = "Generates the HelixLauncher metadata")]
+struct Cli {
+	/// Keep going after a version fails, and report every failure at the end
+	#[arg(long, global = true)]
+	keep_going: bool,
+	/// Also write the failures to errors.json, or to HELIX_META_ERRORS_JSON if set
+	#[arg(long, global = true)]
+	errors_json: bool,
+	/// Never use the network: `all` only processes what was fetched before, and commands that
+	/// have to download something fail
+	#[arg(long, global = true)]
+	offline: bool,
+	/// Also look up the `-sources` and `-javadoc` jars of Maven libraries for the Mojang and
+	/// Fabric components, and list the ones that exist as dev downloads
+	#[arg(long, global = true)]
+	dev_downloads: bool,
+	/// What to do; fetches and processes every source if omitted
+	#[command(subcommand)]
+	command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Fetch and process every source, then check the dependencies between components
+	All,
+	/// Download the upstream metadata of a source
+	Fetch {
+		source: Source,
+		#[command(flatten)]
+		filter: VersionFilter,
+	},
+	/// Generate the components of a source from its downloaded upstream metadata
+	Process {
+		source: Source,
+		#[command(flatten)]
+		filter: VersionFilter,
+	},
+	/// Rebuild every index from the components already generated
+	Reindex {
+		/// Also write search.json next to the indexes
+		#[arg(long)]
+		search: bool,
+	},
+	/// Fetch and process a single version, printing the component
+	Regen { source: Source, version: String },
+	/// Check that every URL in the generated components is reachable
+	ValidateUrls,
+	/// Check that every download is reachable and has the recorded size
+	Verify {
+		/// Also download everything to check the hashes
+		#[arg(long)]
+		deep: bool,
+	},
+	/// Generate a component for an OptiFine installer or mod jar, which can't be downloaded
+	/// automatically, in the local output rather than the published one
+	Optifine { jar: PathBuf },
+	/// Convert generated components to the meta format of Prism Launcher
+	ExportPrism {
+		/// Where to write the Prism meta
+		dir: PathBuf,
+		/// The ids of the components to convert
+		#[arg(required = true)]
+		ids: Vec<String>,
+	},
+	/// Convert components from the meta format of Prism Launcher, looking up the hashes of files
+	/// it has no download for
+	ImportPrism {
+		/// The Prism meta to read
+		dir: PathBuf,
+		/// The uids of the components to convert
+		#[arg(required = true)]
+		uids: Vec<String>,
+	},
+	/// Print the packwiz versions pinning a generated loader component
+	Packwiz {
+		id: String,
+		version: String,
+		/// The Minecraft version, for loaders that don't require a specific one
+		#[arg(long)]
+		minecraft: Option<String>,
+	},
+	/// Write the JSON Schemas of the component and index files to schema/ in the output
+	Schema,
+}
+
+#[derive(clap::Args, Debug)]
+struct VersionFilter {
+	/// Only handle these version ids, leaving the index untouched; mojang only
+	#[arg(long = "only-version", value_delimiter = ',')]
+	only_versions: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Source {
+	Mojang,
+	Forge,
+	Java,
+	Fabric,
+	#[value(name = "legacyfabric")]
+	LegacyFabric,
+	#[value(name = "liteloader")]
+	LiteLoader,
+	/// Not part of `all` yet
+	#[value(name = "neoforge")]
+	NeoForge,
+}
+
+async fn fetch(
+	source: Source,
+	only_versions: &[String],
+	client: &reqwest::Client,
+	config: &config::Config,
+	failures: &report::Failures,
+) -> Result<()> {
+	ensure!(
+		only_versions.is_empty() || matches!(source, Source::Mojang),
+		"--only-version only supports mojang"
+	);
+	match source {
+		Source::Mojang => mojang::fetch(client, config, failures, only_versions).await,
+		Source::Java => java::fetch(client, config, failures).await,
+		Source::Fabric => fabric::fetch(client, config, failures).await,
+		Source::LegacyFabric => legacyfabric::fetch(client, config, failures).await,
+		Source::LiteLoader => liteloader::fetch(client, config, failures).await,
+		Source::NeoForge => neoforge::fetch(client, config, failures).await,
+		// the installers are put into <upstream>/forge/installers by hand
+		Source::Forge => Ok(()),
+	}
+}
+
+/// Unlike [fetch], this gets no HTTP client, so it only ever reads upstream data already on disk.
+fn process(
+	source: Source,
+	only_versions: &[String],
+	config: &config::Config,
+	failures: &report::Failures,
+) -> Result<()> {
+	ensure!(
+		only_versions.is_empty() || matches!(source, Source::Mojang),
+		"--only-version only supports mojang"
+	);
+	fs::create_dir_all(&config.paths.out_root)?;
+	match source {
+		Source::Mojang => mojang::process(config, failures, only_versions),
+		Source::Forge => forge::process(config, failures),
+		Source::Java => java::process(config, failures),
+		Source::Fabric => fabric::process(config, failures),
+		Source::LegacyFabric => legacyfabric::process(config, failures),
+		Source::LiteLoader => liteloader::process(config, failures),
+		Source::NeoForge => neoforge::process(config, failures),
+	}
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-	let client = reqwest::Client::new();
+	let cli = Cli::parse();
+	let config = config::Config {
+		dev_downloads: cli.dev_downloads,
+		..config::Config::from_env()?
+	};
+	// with --offline there is no client at all, so nothing can reach the network by accident
+	let client = (!cli.offline).then(reqwest::Client::new);
+	let client = |command: &str| {
+		client
+			.as_ref()
+			.with_context(|| format!("{command} has to use the network, but --offline is set"))
+	};
+	let failures = report::Failures::new(cli.keep_going);
+
+	match cli.command.unwrap_or(Command::All) {
+		Command::All => {
+			if !cli.offline {
+				let client = client("all")?;
+				fetch(Source::Mojang, &[], client, &config, &failures).await?;
+				fetch(Source::Java, &[], client, &config, &failures).await?;
+				fetch(Source::Fabric, &[], client, &config, &failures).await?;
+				fetch(Source::LegacyFabric, &[], client, &config, &failures).await?;
+				fetch(Source::LiteLoader, &[], client, &config, &failures).await?;
+			}
 
-	mojang::fetch(&client).await?;
+			// sources are processed dependencies first, so that everything a component requires
+			// has been written by the time the requirements are checked. no source reads
+			// another's output (the loaders only refer to other components by id), so they
+			// are processed at the same time; each only writes its own directory under out
+			thread::scope(|scope| {
+				let forge = scope.spawn(|| process(Source::Forge, &[], &config, &failures));
+				let java = scope.spawn(|| process(Source::Java, &[], &config, &failures));
+				let fabric = scope.spawn(|| process(Source::Fabric, &[], &config, &failures));
+				let legacy_fabric =
+					scope.spawn(|| process(Source::LegacyFabric, &[], &config, &failures));
+				let liteloader =
+					scope.spawn(|| process(Source::LiteLoader, &[], &config, &failures));
+				process(Source::Mojang, &[], &config, &failures)?;
+				forge
+					.join()
+					.unwrap_or_else(|panic| panic::resume_unwind(panic))?;
+				fabric
+					.join()
+					.unwrap_or_else(|panic| panic::resume_unwind(panic))?;
+				legacy_fabric
+					.join()
+					.unwrap_or_else(|panic| panic::resume_unwind(panic))?;
+				liteloader
+					.join()
+					.unwrap_or_else(|panic| panic::resume_unwind(panic))?;
+				java.join()
+					.unwrap_or_else(|panic| panic::resume_unwind(panic))
+			})?;
 
-	mojang::process()?;
+			failures.check(
+				"out",
+				"requires",
+				index::check_requires(&config.paths.out_root),
+			)?;
+			index::write_component_index(&config.paths.out_root)?;
+		}
+		Command::Fetch { source, filter } => {
+			fetch(
+				source,
+				&filter.only_versions,
+				client("fetch")?,
+				&config,
+				&failures,
+			)
+			.await?
+		}
+		Command::Process { source, filter } => {
+			process(source, &filter.only_versions, &config, &failures)?;
+			index::write_component_index(&config.paths.out_root)?;
+		}
+		Command::Reindex { search } => {
+			index::reindex(&config.paths.out_root)?;
+			index::write_component_index(&config.paths.out_root)?;
+			if search {
+				index::write_search_index(&config.paths.out_root)?;
+			}
+		}
+		Command::Regen { source, version } => {
+			let component = match source {
+				Source::Mojang => mojang::regen(client("regen")?, &config, &version).await?,
+				_ => bail!("regen only supports mojang"),
+			};
+			println!("{}", serde_json::to_string_pretty(&component)?);
+		}
+		Command::ValidateUrls => validate::validate_urls(client("validate-urls")?, &config).await?,
+		Command::Verify { deep } => validate::verify(client("verify")?, &config, deep).await?,
+		Command::Optifine { jar } => {
+			let component = optifine::process_jar(&config, &jar)?;
+			index::write_component_index(&config.paths.local_root)?;
+			println!("Generated OptiFine {}", component.version);
+		}
+		Command::ExportPrism { dir, ids } => prism::export_all(&config, &failures, &dir, &ids)?,
+		Command::ImportPrism { dir, uids } => {
+			prism::import_all(client("import-prism")?, &config, &failures, &dir, &uids).await?
+		}
+		Command::Packwiz {
+			id,
+			version,
+			minecraft,
+		} => {
+			let component = index::load_component(
+				&config
+					.paths
+					.out_root
+					.join(&id)
+					.join(format!("{version}.json")),
+			)?;
+			print!(
+				"{}",
+				packwiz::pack_versions(&component, minecraft.as_deref())?
+			);
+		}
+		Command::Schema => {
+			let schema_base = &config.paths.out_root.join(index::SCHEMA_DIR);
+			fs::create_dir_all(schema_base)?;
+			write_json(
+				&schema_base.join("component.schema.json"),
+				&helix::schema::component(),
+			)?;
+			write_json(
+				&schema_base.join("index.schema.json"),
+				&helix::schema::index(),
+			)?;
+			write_json(
+				&schema_base.join("component-index.schema.json"),
+				&helix::schema::component_index(),
+			)?;
+		}
+	}
 
+	failures.finish(cli.errors_json.then_some(&config.paths.errors_json))
+}
+
+/// Writes `contents` to `path` unless the file already contains exactly that, so that unchanged
+/// outputs keep their modification time and don't get uploaded again.
+pub fn write_if_changed(path: &Path, contents: &[u8]) -> Result<()> {
+	match fs::read(path) {
+		Ok(existing) if existing == contents => return Ok(()),
+		Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+		_ => {}
+	}
+	fs::write(path, contents)?;
 	Ok(())
 }
+
+pub fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+	// serialized into memory rather than streamed, as it has to be compared anyway
+	write_if_changed(path, &serde_json::to_vec_pretty(value)?)
+}
+
+/// How often [with_retry] tries a request before giving up.
+const RETRY_ATTEMPTS: u32 = 4;
+
+/// Runs `request` until it succeeds, retrying connection failures, timeouts and server errors with
+/// exponential backoff. Anything else, such as a 404 or a hash mismatch, fails immediately.
+pub async fn with_retry<T, F, Fut>(mut request: F) -> Result<T>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = Result<T>>,
+{
+	let mut delay = Duration::from_millis(500);
+	for _ in 1..RETRY_ATTEMPTS {
+		match request().await {
+			Err(error) if is_transient(&error) => {
+				tokio::time::sleep(delay).await;
+				delay *= 2;
+			}
+			result => return result,
+		}
+	}
+	request().await
+}
+
+fn is_transient(error: &anyhow::Error) -> bool {
+	error
+		.chain()
+		.filter_map(|error| error.downcast_ref::<reqwest::Error>())
+		.any(|error| {
+			// a connection reset on a reused keep-alive connection is a request error
+			error.is_connect()
+				|| (error.is_request() && !error.is_builder() && !error.is_redirect())
+				|| error.is_timeout()
+				|| error.is_body()
+				|| error
+					.status()
+					.is_some_and(|status| status.is_server_error())
+		})
+}
+
+/// The validators of a cached download, kept in `<file>.cache` next to it.
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct CacheValidators {
+	etag: Option<String>,
+	last_modified: Option<String>,
+}
+
+/// Downloads `url` to `path` and returns its contents, unless the server answers that the copy
+/// already at `path` is unchanged. The `ETag` and `Last-Modified` of the last download are kept
+/// in `<path>.cache` to make the request conditional.
+pub async fn fetch_cached(client: &reqwest::Client, url: &str, path: &Path) -> Result<Vec<u8>> {
+	let mut cache_path = path.as_os_str().to_owned();
+	cache_path.push(".cache");
+	let cache_path = Path::new(&cache_path);
+
+	let mut request = client.get(url);
+	if path.is_file() {
+		let validators: CacheValidators = match fs::read(cache_path) {
+			Ok(content) => serde_json::from_slice(&content).unwrap_or_default(),
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Default::default(),
+			Err(error) => return Err(error.into()),
+		};
+		if let Some(etag) = validators.etag {
+			request = request.header(IF_NONE_MATCH, etag);
+		}
+		if let Some(last_modified) = validators.last_modified {
+			request = request.header(IF_MODIFIED_SINCE, last_modified);
+		}
+	}
+
+	let response = request.send().await?;
+	if response.status() == StatusCode::NOT_MODIFIED {
+		return Ok(fs::read(path)?);
+	}
+	let response = response.error_for_status()?;
+	let header = |name| {
+		response
+			.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(str::to_owned)
+	};
+	let validators = CacheValidators {
+		etag: header(ETAG),
+		last_modified: header(LAST_MODIFIED),
+	};
+	let content = response.bytes().await?;
+	fs::write(path, &content)?;
+	write_json(cache_path, &validators)?;
+	Ok(content.into())
+}
+
+/// What is known about a released file on a Maven repository, found without downloading it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ArtifactInfo {
+	pub sha1: String,
+	pub size: u32,
+	pub last_modified: DateTime<Utc>,
+}
+
+/// Looks up the SHA-1, size and modification time of the artifact at `url`, from the `.sha1` file
+/// Maven repositories publish next to it and the headers of a HEAD request. If the repository has
+/// no usable `.sha1` file or doesn't send the size, the artifact is downloaded to find them out.
+/// The result is cached at `path`, as released artifacts don't change.
+pub async fn fetch_artifact_info(
+	client: &reqwest::Client,
+	url: &str,
+	path: &Path,
+) -> Result<ArtifactInfo> {
+	if path.is_file() {
+		return serde_json::from_slice(&fs::read(path)?)
+			.with_context(|| format!("Failed to parse {}", path.display()));
+	}
+
+	fn header(response: &reqwest::Response, name: HeaderName) -> Option<String> {
+		response
+			.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(str::to_owned)
+	}
+
+	let response =
+		with_retry(|| async { Ok(client.head(url).send().await?.error_for_status()?) }).await?;
+	// not Response::content_length, which is the length of the (empty) body of the HEAD request
+	let mut size: Option<u32> = header(&response, CONTENT_LENGTH)
+		.map(|size| size.parse())
+		.transpose()
+		.with_context(|| format!("{url} has an invalid size"))?;
+	let mut last_modified = header(&response, LAST_MODIFIED);
+
+	let sha1_url = format!("{url}.sha1");
+	let sha1_file = with_retry(|| async {
+		let response = client.get(&sha1_url).send().await?;
+		if response.status() == StatusCode::NOT_FOUND {
+			return Ok(None);
+		}
+		Ok(Some(response.error_for_status()?.text().await?))
+	})
+	.await?;
+	let mut sha1 = sha1_file.and_then(|sha1_file| {
+		// some repositories append the file name to the hash
+		let sha1 = sha1_file.split_whitespace().next().unwrap_or_default();
+		if sha1.len() == 40 && sha1.bytes().all(|b| b.is_ascii_hexdigit()) {
+			Some(sha1.to_ascii_lowercase())
+		} else {
+			eprintln!("Warning: ignoring {sha1_url}, which is not a SHA-1");
+			None
+		}
+	});
+
+	if size.is_none() || sha1.is_none() {
+		let (content, get_last_modified) = with_retry(|| async {
+			let response = client.get(url).send().await?.error_for_status()?;
+			let last_modified = header(&response, LAST_MODIFIED);
+			Ok((response.bytes().await?, last_modified))
+		})
+		.await?;
+		let actual_sha1 = HEXLOWER.encode(&Sha1::digest(&content));
+		if let Some(sha1) = &sha1 {
+			ensure!(*sha1 == actual_sha1, "{url} doesn't match {sha1_url}");
+		}
+		let actual_size = content.len().try_into()?;
+		if let Some(size) = size {
+			ensure!(
+				size == actual_size,
+				"{url} isn't as long as its Content-Length"
+			);
+		}
+		size = Some(actual_size);
+		sha1 = Some(actual_sha1);
+		last_modified = last_modified.or(get_last_modified);
+	}
+
+	let last_modified =
+		last_modified.with_context(|| format!("{url} has no Last-Modified header"))?;
+	let last_modified = DateTime::parse_from_rfc2822(&last_modified)
+		.with_context(|| format!("{url} has an invalid Last-Modified"))?
+		.with_timezone(&Utc);
+
+	let info = ArtifactInfo {
+		sha1: sha1.unwrap(),
+		size: size.unwrap(),
+		last_modified,
+	};
+	write_json(path, &info)?;
+	Ok(info)
+}
+
+/// Where the [ArtifactInfo] of `name` is cached under `upstream_base`. Libraries are usually
+/// shared between versions, so they are cached once for all of them.
+pub fn artifact_info_path(upstream_base: &Path, name: &GradleSpecifier) -> PathBuf {
+	upstream_base
+		.join("artifacts")
+		.join(format!("{}.{}.json", name.group, name.file_name()))
+}
+
+/// Builds the download of `name` from its cached [ArtifactInfo], which is returned as well.
+pub fn artifact_download(
+	upstream_base: &Path,
+	name: &GradleSpecifier,
+	url: String,
+) -> Result<(helix::component::Download, ArtifactInfo)> {
+	let path = artifact_info_path(upstream_base, name);
+	let info: ArtifactInfo = serde_json::from_slice(
+		&fs::read(&path).with_context(|| format!("{name} was not fetched"))?,
+	)
+	.with_context(|| format!("Failed to parse {}", path.display()))?;
+	Ok((
+		helix::component::Download {
+			name: name.clone(),
+			url,
+			size: info.size,
+			hash: helix::component::Hash::parse_sha1(&info.sha1)?,
+		},
+		info,
+	))
+}
+
+/// The classifiers looked up for the
+/// [dev downloads](helix::component::Component::dev_downloads) of a library.
+const DEV_CLASSIFIERS: [&str; 2] = ["sources", "javadoc"];
+
+/// Returns the base of the Maven repository the library `name` is downloaded from at `url`, or
+/// `None` if it can't have dev downloads, because it has a classifier already, isn't a jar, or
+/// `url` doesn't follow the Maven layout.
+fn dev_download_base<'a>(name: &GradleSpecifier, url: &'a str) -> Option<&'a str> {
+	if name.classifier.is_some() || name.extension != "jar" {
+		return None;
+	}
+	url.strip_suffix(&name.to_url(""))
+}
+
+/// Where it is cached that the artifact whose [ArtifactInfo] would be at `path` doesn't exist.
+fn missing_artifact_path(path: &Path) -> PathBuf {
+	path.with_extension("missing")
+}
+
+/// Looks up the `-sources` and `-javadoc` jars of the library `name` downloaded from `url`, for
+/// [add_dev_downloads]. Most libraries have neither, so a 404 isn't an error; it is cached next to
+/// where the [ArtifactInfo] would be, so the jar isn't asked for again.
+pub async fn fetch_dev_artifact_infos(
+	client: &reqwest::Client,
+	upstream_base: &Path,
+	name: &GradleSpecifier,
+	url: &str,
+) -> Result<()> {
+	let Some(base) = dev_download_base(name, url) else {
+		return Ok(());
+	};
+	for classifier in DEV_CLASSIFIERS {
+		let dev_name = name.with_classifier(classifier.into());
+		let path = artifact_info_path(upstream_base, &dev_name);
+		let missing_path = missing_artifact_path(&path);
+		if missing_path.is_file() {
+			continue;
+		}
+		match fetch_artifact_info(client, &dev_name.to_url(base), &path).await {
+			Err(error) if is_not_found(&error) => write_if_changed(&missing_path, b"")?,
+			result => {
+				result?;
+			}
+		}
+	}
+	Ok(())
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+	error
+		.chain()
+		.filter_map(|error| error.downcast_ref::<reqwest::Error>())
+		.any(|error| error.status() == Some(StatusCode::NOT_FOUND))
+}
+
+/// Adds the `-sources` and `-javadoc` jars found by [fetch_dev_artifact_infos] for the downloads of
+/// `component` to its [dev downloads](helix::component::Component::dev_downloads). Libraries that
+/// weren't looked up, because they were fetched without `--dev-downloads`, are left out.
+pub fn add_dev_downloads(
+	component: &mut helix::component::Component,
+	upstream_base: &Path,
+) -> Result<()> {
+	for download in &component.downloads {
+		let Some(base) = dev_download_base(&download.name, &download.url) else {
+			continue;
+		};
+		for classifier in DEV_CLASSIFIERS {
+			let name = download.name.with_classifier(classifier.into());
+			if artifact_info_path(upstream_base, &name).is_file() {
+				let url = name.to_url(base);
+				component
+					.dev_downloads
+					.push(artifact_download(upstream_base, &name, url)?.0);
+			}
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		env,
+		io::{Read, Write},
+		net::TcpListener,
+		sync::atomic::{AtomicU32, Ordering},
+	};
+
+	use super::*;
+
+	/// Serves every connection on a local port with the next of `responses`, where `None` closes
+	/// the connection without answering, like a reset keep-alive connection.
+	fn serve(responses: Vec<Option<&'static str>>) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}/", listener.local_addr().unwrap());
+		thread::spawn(move || {
+			for response in responses {
+				let (mut stream, _) = listener.accept().unwrap();
+				let mut buffer = [0; 4096];
+				let _ = stream.read(&mut buffer);
+				if let Some(response) = response {
+					let _ = stream.write_all(response.as_bytes());
+				}
+			}
+		});
+		url
+	}
+
+	#[tokio::test]
+	async fn with_retry_retries_transient_failures() {
+		const UNAVAILABLE: &str =
+			"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+		const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+		let url = serve(vec![None, Some(UNAVAILABLE), Some(OK)]);
+		let client = reqwest::Client::new();
+		let attempts = AtomicU32::new(0);
+
+		let body = with_retry(|| async {
+			attempts.fetch_add(1, Ordering::Relaxed);
+			Ok(client
+				.get(&url)
+				.send()
+				.await?
+				.error_for_status()?
+				.text()
+				.await?)
+		})
+		.await
+		.unwrap();
+
+		assert_eq!(body, "ok");
+		assert_eq!(attempts.into_inner(), 3);
+	}
+
+	const LAST_MODIFIED: &str = "Last-Modified: Tue, 15 Nov 1994 08:12:31 GMT\r\n";
+	const NOT_FOUND: &str =
+		"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+	const HELLO: &str = "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello";
+	const HELLO_SHA1: &str = "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
+
+	async fn artifact_info(
+		responses: Vec<Option<&'static str>>,
+		name: &str,
+	) -> Result<ArtifactInfo> {
+		let url = serve(responses);
+		let path = env::temp_dir().join(format!(
+			"helix-meta-test-{}-{name}.json",
+			std::process::id()
+		));
+		let info = fetch_artifact_info(&reqwest::Client::new(), &url, &path).await;
+		let _ = fs::remove_file(path);
+		info
+	}
+
+	#[tokio::test]
+	async fn artifact_info_without_sha1_file_or_size_hashes_the_artifact() {
+		let head = format!("HTTP/1.1 200 OK\r\n{LAST_MODIFIED}Connection: close\r\n\r\n").leak();
+		let info = artifact_info(vec![Some(head), Some(NOT_FOUND), Some(HELLO)], "missing")
+			.await
+			.unwrap();
+		assert_eq!(info.sha1, HELLO_SHA1);
+		assert_eq!(info.size, 5);
+	}
+
+	#[tokio::test]
+	async fn artifact_info_ignores_invalid_sha1_files() {
+		let head = format!(
+			"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n{LAST_MODIFIED}Connection: close\r\n\r\n"
+		)
+		.leak();
+		let sha1_file =
+			"HTTP/1.1 200 OK\r\nContent-Length: 9\r\nConnection: close\r\n\r\nnot found";
+		let info = artifact_info(vec![Some(head), Some(sha1_file), Some(HELLO)], "invalid")
+			.await
+			.unwrap();
+		assert_eq!(info.sha1, HELLO_SHA1);
+	}
+
+	#[tokio::test]
+	async fn artifact_info_rejects_artifacts_not_matching_the_sha1_file() {
+		let head = format!("HTTP/1.1 200 OK\r\n{LAST_MODIFIED}Connection: close\r\n\r\n").leak();
+		let sha1_file = format!(
+			"HTTP/1.1 200 OK\r\nContent-Length: 40\r\nConnection: close\r\n\r\n{}",
+			"0".repeat(40)
+		)
+		.leak();
+		let result =
+			artifact_info(vec![Some(head), Some(sha1_file), Some(HELLO)], "mismatch").await;
+		assert!(result.is_err());
+	}
+
+	#[tokio::test]
+	async fn dev_downloads_skip_missing_jars() {
+		let head = format!(
+			"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n{LAST_MODIFIED}Connection: close\r\n\r\n"
+		)
+		.leak();
+		let sha1_file = format!(
+			"HTTP/1.1 200 OK\r\nContent-Length: 40\r\nConnection: close\r\n\r\n{HELLO_SHA1}"
+		)
+		.leak();
+		// the sources jar doesn't exist, the javadoc jar does
+		let base = serve(vec![Some(NOT_FOUND), Some(head), Some(sha1_file)]);
+		let upstream_base =
+			env::temp_dir().join(format!("helix-meta-test-{}-dev", std::process::id()));
+		fs::create_dir_all(upstream_base.join("artifacts")).unwrap();
+		let name: GradleSpecifier = "org.example:library:1.0".parse().unwrap();
+		let url = name.to_url(&base);
+
+		fetch_dev_artifact_infos(&reqwest::Client::new(), &upstream_base, &name, &url)
+			.await
+			.unwrap();
+		let mut component =
+			helix::component::Component::new("org.example", "1.0", DateTime::UNIX_EPOCH);
+		component.downloads.push(helix::component::Download {
+			name,
+			url,
+			size: 5,
+			hash: helix::component::Hash::parse_sha1(HELLO_SHA1).unwrap(),
+		});
+		let result = add_dev_downloads(&mut component, &upstream_base);
+		let _ = fs::remove_dir_all(&upstream_base);
+		result.unwrap();
+
+		let dev_urls: Vec<_> = component
+			.dev_downloads
+			.iter()
+			.map(|download| download.url.clone())
+			.collect();
+		assert_eq!(
+			dev_urls,
+			[format!(
+				"{base}org/example/library/1.0/library-1.0-javadoc.jar"
+			)]
+		);
+		assert_eq!(component.dev_downloads[0].size, 5);
+	}
+
+	#[tokio::test]
+	async fn with_retry_gives_up_on_client_errors() {
+		let url = serve(vec![Some(NOT_FOUND)]);
+		let client = reqwest::Client::new();
+		let attempts = AtomicU32::new(0);
+
+		let result = with_retry(|| async {
+			attempts.fetch_add(1, Ordering::Relaxed);
+			Ok(client.get(&url).send().await?.error_for_status()?)
+		})
+		.await;
+
+		assert!(result.is_err());
+		assert_eq!(attempts.into_inner(), 1);
+	}
+}
@@ -5,8 +5,12 @@
  */
 
 use std::borrow::Cow;
-use std::collections::BTreeSet;
-use std::{fs, path::Path};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::{
+	fs,
+	io::Read,
+	path::{Path, PathBuf},
+};
 
 use anyhow::{bail, ensure, Context, Result};
 use chrono::{DateTime, Utc};
@@ -16,8 +20,8 @@ use helix::component::{ConditionFeature, MinecraftArgument};
 use indexmap::{IndexMap, IndexSet};
 use lazy_static::lazy_static;
 use maven_version::Maven3ArtifactVersion;
+use rayon::prelude::*;
 use regex::{Captures, Regex};
-use serde::de::IgnoredAny;
 use serde::Deserialize;
 use serde_with::{serde_as, OneOrMany};
 use sha1::{Digest, Sha1};
@@ -26,6 +30,11 @@ use helixlauncher_meta as helix;
 use helixlauncher_meta::component::OsName;
 use helixlauncher_meta::util::GradleSpecifier;
 
+use crate::{
+	add_dev_downloads, config::Config, fetch_cached, fetch_dev_artifact_infos, index,
+	report::Failures, with_retry,
+};
+
 #[derive(Deserialize, Debug, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 enum VersionType {
@@ -57,9 +66,16 @@ struct VersionManifestVersion {
 
 #[derive(Deserialize, Debug)]
 struct VersionManifest {
+	pub latest: VersionManifestLatest,
 	pub versions: Vec<VersionManifestVersion>,
 }
 
+#[derive(Deserialize, Debug)]
+struct VersionManifestLatest {
+	pub release: String,
+	pub snapshot: String,
+}
+
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 enum RuleAction {
@@ -94,19 +110,10 @@ pub struct Rule {
 	action: RuleAction,
 }
 
-impl Rule {
-	fn is_always_allow(&self) -> bool {
-		match self.action {
-			RuleAction::Allow => self.features.is_none() && self.os.is_none(),
-			_ => false,
-		}
-	}
-}
-
 #[serde_as]
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
-enum MojangConditionalValue<T> {
+pub enum MojangConditionalValue<T> {
 	Always(T),
 	Conditional {
 		rules: Vec<Rule>,
@@ -117,8 +124,10 @@ enum MojangConditionalValue<T> {
 
 #[derive(Deserialize, Debug)]
 pub struct MojangVersionArguments {
-	game: Vec<MojangConditionalValue<String>>,
-	jvm: Vec<MojangConditionalValue<String>>,
+	pub game: Vec<MojangConditionalValue<String>>,
+	// Forge versions before 1.17 only have game arguments
+	#[serde(default)]
+	pub jvm: Vec<MojangConditionalValue<String>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -150,16 +159,29 @@ struct MojangDownload {
 	url: String,
 }
 
+/// Lists every download key found across the version manifests. Only `client` is needed to launch
+/// the game; the others are server or deobfuscation files. Keys added later end up in `unknown`,
+/// which [process_version] warns about, so that they are noticed without failing the version.
 #[derive(Deserialize, Debug)]
 pub struct MojangDownloads {
 	client: MojangDownload,
+	#[serde(rename = "client_mappings")]
+	_client_mappings: Option<MojangDownload>,
+	#[serde(rename = "server")]
+	_server: Option<MojangDownload>,
+	#[serde(rename = "server_mappings")]
+	_server_mappings: Option<MojangDownload>,
+	#[serde(rename = "windows_server")]
+	_windows_server: Option<MojangDownload>,
+	#[serde(flatten)]
+	unknown: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Debug)]
 struct MojangJavaVersion {
 	component: String,
 	#[serde(rename = "majorVersion")]
-	major_version: i32,
+	major_version: u32,
 }
 
 #[derive(Deserialize, Debug)]
@@ -198,13 +220,33 @@ pub struct MojangLibrary {
 }
 
 #[derive(Deserialize, Debug)]
-struct MojangLogging {}
+#[serde(deny_unknown_fields)]
+struct MojangLoggingFile {
+	id: String,
+	sha1: String,
+	size: u32,
+	url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MojangLoggingConfig {
+	argument: String,
+	file: MojangLoggingFile,
+	#[serde(rename = "type")]
+	kind: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MojangLogging {
+	// Forge versions have an empty logging object
+	client: Option<MojangLoggingConfig>,
+}
 
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct MojangVersion {
-	#[serde(rename = "_comment_", default)]
-	_comment: IgnoredAny,
 	pub inherits_from: Option<String>,
 	pub arguments: Option<MojangVersionArguments>,
 	pub asset_index: Option<MojangAssetIndex>,
@@ -219,138 +261,698 @@ pub struct MojangVersion {
 	pub minecraft_arguments: Option<String>,
 	_minimum_launcher_version: Option<i32>,
 	pub release_time: DateTime<Utc>,
-	time: DateTime<Utc>,
+	_time: DateTime<Utc>,
 	#[serde(rename = "type")]
 	version_type: VersionType,
 }
 
+impl MojangVersion {
+	/// Parses a version manifest, dropping top-level keys starting with `_` first. Mojang uses
+	/// those for comments, and [MojangVersion] rejects every other unknown key.
+	pub fn from_reader(reader: impl Read) -> Result<Self> {
+		let mut value: serde_json::Value = serde_json::from_reader(reader)?;
+		if let Some(object) = value.as_object_mut() {
+			object.retain(|key, _| !key.starts_with('_'));
+		}
+		Ok(serde_json::from_value(value)?)
+	}
+}
+
+fn read_version(path: &Path) -> Result<MojangVersion> {
+	let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+	MojangVersion::from_reader(&*content)
+		.with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Merges a version with the version it inherits from, the way the vanilla launcher does.
+///
+/// Fields set in `child` win. Libraries of the child come first, and libraries of the parent with
+/// the same [GradleSpecifier] as one of the child's are dropped. Arguments are concatenated, parent
+/// first.
+fn merge_parent(child: MojangVersion, parent: MojangVersion) -> MojangVersion {
+	let child_libraries: HashSet<_> = child
+		.libraries
+		.iter()
+		.map(|library| library.name.clone())
+		.collect();
+	let mut libraries = child.libraries;
+	libraries.extend(
+		parent
+			.libraries
+			.into_iter()
+			.filter(|library| !child_libraries.contains(&library.name)),
+	);
+
+	let arguments = match (parent.arguments, child.arguments) {
+		(Some(mut parent), Some(child)) => {
+			parent.game.extend(child.game);
+			parent.jvm.extend(child.jvm);
+			Some(parent)
+		}
+		(parent, child) => child.or(parent),
+	};
+
+	MojangVersion {
+		inherits_from: parent.inherits_from,
+		arguments,
+		asset_index: child.asset_index.or(parent.asset_index),
+		_assets: child._assets.or(parent._assets),
+		_compliance_level: child._compliance_level.or(parent._compliance_level),
+		downloads: child.downloads.or(parent.downloads),
+		id: child.id,
+		java_version: child.java_version.or(parent.java_version),
+		libraries,
+		logging: child.logging.or(parent.logging),
+		main_class: child.main_class,
+		minecraft_arguments: child.minecraft_arguments.or(parent.minecraft_arguments),
+		_minimum_launcher_version: child
+			._minimum_launcher_version
+			.or(parent._minimum_launcher_version),
+		release_time: child.release_time,
+		_time: child._time,
+		version_type: child.version_type,
+	}
+}
+
+/// What a placeholder in Mojang's arguments is replaced with.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(from = "String")]
+pub enum Placeholder {
+	/// Replaced with this text, usually the equivalent Helix placeholder.
+	Text(Cow<'static, str>),
+	/// Removed, as Helix has no equivalent.
+	Empty,
+	/// Replaced with the type of the version, which Helix has no placeholder for.
+	VersionType,
+}
+
+impl From<String> for Placeholder {
+	fn from(text: String) -> Self {
+		if text.is_empty() {
+			Self::Empty
+		} else {
+			Self::Text(text.into())
+		}
+	}
+}
+
+impl Placeholder {
+	fn replacement<'a>(&'a self, version: &MojangVersion) -> &'a str {
+		match self {
+			Self::Text(text) => text,
+			Self::Empty => "",
+			Self::VersionType => version.version_type.as_str(),
+		}
+	}
+}
+
+const fn text(text: &'static str) -> Placeholder {
+	Placeholder::Text(Cow::Borrowed(text))
+}
+
+/// The built-in placeholder mappings, which `placeholders.json` can add to or replace.
+const PLACEHOLDERS: &[(&str, Placeholder)] = &[
+	("${auth_access_token}", text("${user.token}")),
+	("${auth_player_name}", text("${user.name}")),
+	("${version_name}", text("${instance.minecraft_version}")),
+	("${game_directory}", text("${instance.game_dir}")),
+	("${assets_root}", text("${instance.assets_dir}")),
+	(
+		"${assets_index_name}",
+		text("${instance.assets_index_name}"),
+	),
+	("${auth_uuid}", text("${user.uuid}")),
+	("${clientid}", Placeholder::Empty),
+	("${auth_xuid}", Placeholder::Empty),
+	("${auth_session}", text("${user.token}")), // TODO: is this really just the token?
+	("${user_type}", text("${user.type}")),     // TODO: what is this?
+	("${version_type}", Placeholder::VersionType),
+	("${resolution_width}", text("${window.width}")),
+	("${resolution_height}", text("${window.height}")),
+	// was used for twitch integration which is no longer a thing
+	("${user_properties}", text("{}")),
+	("${game_assets}", text("${instance.virtual_assets_dir}")),
+	("${quickPlaySingleplayer}", text("${launch.world}")),
+	("${quickPlayMultiplayer}", text("${launch.server}")),
+	("${natives_directory}", text("${instance.natives_dir}")),
+	("${launcher_name}", text("${launcher.name}")),
+	("${launcher_version}", text("${launcher.version}")),
+	("${classpath}", text("${launch.classpath}")),
+	("${path}", text("${launch.logging_config}")),
+];
+
+/// Mojang placeholder, with the `${}` around it -> what it is replaced with.
+pub type Placeholders = HashMap<String, Placeholder>;
+
+lazy_static! {
+	static ref VAR_PATTERN: Regex = Regex::new("(\\$\\{[a-zA-Z0-9_]+\\})").unwrap();
+}
+
+fn builtin_placeholders() -> Placeholders {
+	PLACEHOLDERS
+		.iter()
+		.map(|(token, placeholder)| ((*token).to_owned(), placeholder.clone()))
+		.collect()
+}
+
+/// Reads the built-in placeholder mappings, with the ones in `path` (usually `placeholders.json`)
+/// on top if it exists. An empty string there removes the placeholder.
+pub fn load_placeholders(path: &Path) -> Result<Placeholders> {
+	let mut placeholders = builtin_placeholders();
+	let extra: Placeholders = match fs::read_to_string(path) {
+		Ok(content) => serde_json::from_str(&content)
+			.with_context(|| format!("Failed to parse {}", path.display()))?,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(placeholders),
+		Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+	};
+	for token in extra.keys() {
+		ensure!(
+			VAR_PATTERN
+				.find(token)
+				.is_some_and(|m| m.len() == token.len()),
+			"{token} in {} is not a placeholder",
+			path.display()
+		);
+	}
+	placeholders.extend(extra);
+	Ok(placeholders)
+}
+
+fn remap_vars<'a>(
+	s: &'a str,
+	version: &MojangVersion,
+	placeholders: &Placeholders,
+) -> Result<Cow<'a, str>, UnknownPlaceholder> {
+	if let Some(unknown) = VAR_PATTERN
+		.find_iter(s)
+		.find(|m| !placeholders.contains_key(m.as_str()))
+	{
+		return Err(UnknownPlaceholder {
+			token: unknown.as_str().to_owned(),
+			argument: s.to_owned(),
+		});
+	}
+	Ok(VAR_PATTERN.replace_all(s, |c: &Captures<'_>| {
+		placeholders[c.get(1).unwrap().as_str()].replacement(version)
+	}))
+}
+
+/// A placeholder in an argument that [process_version] doesn't know the Helix equivalent of.
+#[derive(thiserror::Error, Debug)]
+#[error("Unknown placeholder {token} in argument {argument}")]
+pub struct UnknownPlaceholder {
+	pub token: String,
+	pub argument: String,
+}
+
 mod rules {
 	use super::{OsName, Rule, RuleAction};
+	use helixlauncher_meta::component::{Arch, Platform};
 	use thiserror::Error;
 
 	#[derive(Error, Debug)]
 	pub enum Error {
 		#[error("Unsupported feature: {0}")]
 		UnsupportedFeature(&'static str),
+		#[error("Unknown architecture {0}")]
+		UnknownArch(String),
 	}
 
-	pub(super) fn evaluate_rules_os_name(rules: &[Rule]) -> Result<Vec<OsName>, Error> {
-		let mut result = vec![];
-		for current_os in [OsName::Linux, OsName::Osx, OsName::Windows] {
-			let mut allow = false;
-			for rule in rules {
-				if let Some(os) = &rule.os {
-					if os.arch.is_some() {
-						return Err(Error::UnsupportedFeature("os.arch"));
-					}
-					if os.version.is_some() {
-						return Err(Error::UnsupportedFeature("os.version"));
-					}
-					if let Some(osname) = os.name {
-						if osname != current_os {
-							continue;
-						}
-					}
+	const OS_NAMES: [OsName; 3] = [OsName::Linux, OsName::Osx, OsName::Windows];
+	const ARCHES: [Arch; 5] = [
+		Arch::X86,
+		Arch::X86_64,
+		Arch::Arm32,
+		Arch::Arm64,
+		Arch::Riscv64,
+	];
+
+	pub(super) fn parse_arch(arch: &str) -> Result<Arch, Error> {
+		match arch {
+			"x86" => Ok(Arch::X86),
+			"x86_64" | "amd64" => Ok(Arch::X86_64),
+			"arm32" => Ok(Arch::Arm32),
+			"arm64" | "aarch64" => Ok(Arch::Arm64),
+			"riscv64" => Ok(Arch::Riscv64),
+			_ => Err(Error::UnknownArch(arch.to_owned())),
+		}
+	}
+
+	/// The architecture of an LWJGL 3 natives jar, from a classifier like `natives-macos-arm64`, or
+	/// `None` if `classifier` isn't one of those. Natives without an architecture are for x86_64.
+	pub(super) fn natives_classifier_arch(classifier: &str) -> Result<Option<Arch>, Error> {
+		let Some(platform) = classifier.strip_prefix("natives-") else {
+			return Ok(None);
+		};
+		match platform.split_once('-') {
+			None => Ok(Some(Arch::X86_64)),
+			Some((_, arch)) => parse_arch(arch).map(Some),
+		}
+	}
+
+	/// Whether `rules` allow `os` and `arch`, and if so, the `os.version` regex of the rule that
+	/// allowed it. Rules with an `os.arch` never match a `None` arch.
+	///
+	/// A platform can only require an OS version, not exclude one, so disallow rules with an
+	/// `os.version` are ignored with a warning. The library then applies to every version of
+	/// that OS, which is right for the common case of a current OS.
+	fn is_allowed(
+		rules: &[Rule],
+		os: OsName,
+		arch: Option<Arch>,
+	) -> Result<Option<Option<&str>>, Error> {
+		let mut allowed = None;
+		for rule in rules {
+			let mut version = None;
+			if let Some(rule_os) = &rule.os {
+				if rule_os.name.is_some_and(|name| name != os) {
+					continue;
 				}
-				if rule.features.is_some() {
-					return Err(Error::UnsupportedFeature("features"));
+				if let Some(rule_arch) = &rule_os.arch {
+					if Some(parse_arch(rule_arch)?) != arch {
+						continue;
+					}
 				}
-				allow = match rule.action {
-					RuleAction::Allow => true,
-					RuleAction::Disallow => false,
+				version = rule_os.version.as_deref();
+			}
+			if rule.features.is_some() {
+				return Err(Error::UnsupportedFeature("features"));
+			}
+			allowed = match (&rule.action, version) {
+				(RuleAction::Allow, version) => Some(version),
+				(RuleAction::Disallow, None) => None,
+				(RuleAction::Disallow, Some(version)) => {
+					eprintln!("Warning: ignoring rule disallowing {os:?} version {version}");
+					continue;
 				}
+			};
+		}
+		Ok(allowed)
+	}
+
+	/// Returns the platforms `rules` allow for `arch`, one per distinct `os.version` regex.
+	fn allowed_platforms(rules: &[Rule], arch: Option<Arch>) -> Result<Vec<Platform>, Error> {
+		let mut platforms: Vec<Platform> = vec![];
+		for os in OS_NAMES {
+			let Some(version) = is_allowed(rules, os, arch)? else {
+				continue;
+			};
+			match platforms
+				.iter_mut()
+				.find(|platform| platform.os_version.as_deref() == version)
+			{
+				Some(platform) => platform.os.push(os),
+				None => platforms.push(Platform {
+					os: vec![os],
+					arch,
+					os_version: version.map(str::to_owned),
+				}),
 			}
-			if allow {
-				result.push(current_os);
+		}
+		Ok(platforms)
+	}
+
+	/// Returns the platforms `rules` allow, or an empty list if they allow none. Unless a rule
+	/// has an `os.arch` or `os.version`, this is at most a single platform without an
+	/// architecture.
+	pub(super) fn evaluate_rules_platforms(rules: &[Rule]) -> Result<Vec<Platform>, Error> {
+		if rules
+			.iter()
+			.any(|rule| rule.os.as_ref().is_some_and(|os| os.arch.is_some()))
+		{
+			let mut platforms = vec![];
+			for arch in ARCHES {
+				platforms.extend(allowed_platforms(rules, Some(arch))?);
 			}
+			Ok(platforms)
+		} else {
+			allowed_platforms(rules, None)
 		}
-		Ok(result)
 	}
 }
 
-const CONCURRENT_FETCH_LIMIT: Option<usize> = Some(5);
+/// Parses a version for use in range checks.
+///
+/// [Maven3ArtifactVersion] accepts any string and orders unusual ones (build metadata, non-numeric
+/// schemes) in surprising places, so anything not of the form `1.2.3` with an optional `-qualifier`
+/// is rejected instead of being compared.
+fn parse_maven_version(version: &str) -> Result<Maven3ArtifactVersion<'_>> {
+	lazy_static! {
+		static ref MAVEN_VERSION_PATTERN: Regex =
+			Regex::new("^[0-9]+(?:\\.[0-9]+)*(?:-[0-9A-Za-z][0-9A-Za-z.-]*)?$").unwrap();
+	}
+	ensure!(
+		MAVEN_VERSION_PATTERN.is_match(version),
+		"Unsupported version format \"{version}\""
+	);
+	Ok(Maven3ArtifactVersion::new(version))
+}
+
+async fn fetch_manifest(client: &reqwest::Client, config: &Config) -> Result<VersionManifest> {
+	let manifest_path = config
+		.paths
+		.upstream_root
+		.join("mojang/version_manifest_v2.json");
+	let content = with_retry(|| {
+		fetch_cached(
+			client,
+			"https://piston-meta.mojang.com/mc/game/version_manifest_v2.json",
+			&manifest_path,
+		)
+	})
+	.await?;
+	Ok(serde_json::from_slice(&content)?)
+}
 
-pub async fn fetch(client: &reqwest::Client) -> Result<()> {
-	let version_base = Path::new("upstream/mojang/versions");
+/// Downloads every version in the manifest, or only those in `only_versions` if it isn't empty.
+/// With [Config::dev_downloads], the dev downloads of their libraries are looked up as well.
+pub async fn fetch(
+	client: &reqwest::Client,
+	config: &Config,
+	failures: &Failures,
+	only_versions: &[String],
+) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join("mojang");
+	let version_base = &upstream_base.join("versions");
 	fs::create_dir_all(version_base)?;
+	if config.dev_downloads {
+		fs::create_dir_all(upstream_base.join("artifacts"))?;
+	}
 
-	let version_manifest: VersionManifest = client
-		.get("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json")
-		.send()
-		.await?
-		.json()
-		.await?;
+	let mut versions = fetch_manifest(client, config).await?.versions;
+	if !only_versions.is_empty() {
+		versions.retain(|version| only_versions.contains(&version.id));
+		for id in only_versions {
+			ensure!(
+				versions.iter().any(|version| &version.id == id),
+				"{id} is not in the version manifest"
+			);
+		}
+	}
 
-	futures::stream::iter(version_manifest.versions)
+	futures::stream::iter(versions)
 		.map(Ok)
-		.try_for_each_concurrent(CONCURRENT_FETCH_LIMIT, |v| async move {
-			fetch_version(client, version_base, v).await
+		.try_for_each_concurrent(config.concurrency, |v| async move {
+			let id = v.id.clone();
+			let result = async {
+				let path = fetch_version(client, version_base, v).await?;
+				if config.dev_downloads {
+					fetch_dev_libraries(client, upstream_base, &path).await?;
+				}
+				Ok(())
+			};
+			failures.check("mojang", id, result.await)?;
+			Ok(())
 		})
 		.await
 }
 
+/// Downloads a version to `version_base`, unless it is there already, and returns its path.
 async fn fetch_version(
 	client: &reqwest::Client,
 	version_base: &Path,
 	version: VersionManifestVersion,
-) -> Result<()> {
+) -> Result<PathBuf> {
 	let version_path = version_base.join(format!("{}.json", version.id));
 
+	// the manifest pins every version file by its SHA-1, so unlike the manifest itself, a cached
+	// version can be checked without asking the server
 	if version_path.try_exists()? {
 		let content = fs::read(&version_path)?;
 		if HEXLOWER.encode(&Sha1::digest(content)) == version.sha1 {
-			return Ok(());
+			return Ok(version_path);
 		}
 	}
-	let content = client.get(version.url).send().await?.bytes().await?;
+	let content = with_retry(|| async {
+		Ok(client
+			.get(&version.url)
+			.send()
+			.await?
+			.error_for_status()?
+			.bytes()
+			.await?)
+	})
+	.await?;
 	if HEXLOWER.encode(&Sha1::digest(&content)) != version.sha1 {
 		bail!("{} has wrong SHA-1!", version.id)
 	}
-	fs::write(version_path, content)?;
+	fs::write(&version_path, content)?;
 
+	Ok(version_path)
+}
+
+/// Looks up the dev downloads of the libraries of the version at `path`, see
+/// [crate::fetch_dev_artifact_infos].
+async fn fetch_dev_libraries(
+	client: &reqwest::Client,
+	upstream_base: &Path,
+	path: &Path,
+) -> Result<()> {
+	let version = read_version(path)?;
+	for library in &version.libraries {
+		if let Some(artifact) = &library.downloads.artifact {
+			fetch_dev_artifact_infos(client, upstream_base, &library.name, &artifact.url).await?;
+		}
+	}
 	Ok(())
 }
 
-pub fn process() -> Result<()> {
-	let version_base = Path::new("upstream/mojang/versions");
-	let out_base = Path::new("out/net.minecraft");
+/// Processes every fetched version and writes the index. If `only_versions` isn't empty, only
+/// those versions are processed and the index is left untouched, as it would be incomplete.
+pub fn process(config: &Config, failures: &Failures, only_versions: &[String]) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join("mojang");
+	let version_base = &upstream_base.join("versions");
+	let out_base = &config.paths.out_root.join("net.minecraft");
 	fs::create_dir_all(out_base)?;
 
-	let mut index: helix::index::Index = vec![];
+	let overrides = load_overrides(&config.paths.overrides)?;
+	let placeholders = load_placeholders(&config.paths.placeholders)?;
+	let process_file = |path: &Path| {
+		let mut component = process_version(path, &overrides, &placeholders)?;
+		if config.dev_downloads {
+			add_dev_downloads(&mut component, upstream_base)?;
+		}
+		anyhow::Ok(component)
+	};
 
-	for file in fs::read_dir(version_base)? {
-		let file = file?;
-		let component = process_version(&file, out_base)
-			.with_context(|| format!("Failed to process {}", file.file_name().to_str().unwrap()))?;
-		index.push(component.into());
+	if !only_versions.is_empty() {
+		for id in only_versions {
+			let path = version_base.join(format!("{id}.json"));
+			if let Some(component) = failures.check("mojang", id, process_file(&path))? {
+				index::write_component(out_base, &component)?;
+			}
+		}
+		return Ok(());
+	}
+
+	let mut files = fs::read_dir(version_base)?
+		.map(|file| Ok(file?.file_name().to_str().unwrap().to_owned()))
+		.collect::<Result<Vec<_>>>()?;
+	// sorted, so that which of two files declaring the same id is reported doesn't depend on
+	// directory iteration order
+	files.sort();
+
+	// versions are independent of each other, so they are processed in parallel and only checked
+	// against each other afterwards
+	let components = files
+		.into_par_iter()
+		.map(|file_name| {
+			let component = failures.check(
+				"mojang",
+				&file_name,
+				process_file(&version_base.join(&file_name)),
+			)?;
+			Ok(component.map(|component| (file_name, component)))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	// version id -> file that declared it, so that two files with the same id don't overwrite
+	// each other
+	let mut sources = HashMap::new();
+	for (file_name, component) in components.iter().flatten() {
+		if let Some(previous) = sources.insert(&component.version, file_name) {
+			bail!(
+				"{previous} and {file_name} both declare version {}",
+				component.version
+			);
+		}
+	}
+
+	// the order the versions were processed in doesn't matter, write_index sorts the entries
+	let mut index = components
+		.par_iter()
+		.flatten()
+		.map(|(_, component)| index::write_component(out_base, component))
+		.collect::<Result<helix::index::Index>>()?;
+
+	// the manifest was saved when fetching, so it matches the fetched versions
+	let manifest_path = config
+		.paths
+		.upstream_root
+		.join("mojang/version_manifest_v2.json");
+	let manifest: VersionManifest = serde_json::from_slice(&fs::read(&manifest_path)?)
+		.with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+	for (channel, id) in [
+		("release", &manifest.latest.release),
+		("snapshot", &manifest.latest.snapshot),
+	] {
+		// a latest version that failed to process has been reported already
+		if let Some(entry) = index.iter_mut().find(|entry| &entry.version == id) {
+			entry.latest.push(channel.into());
+		}
 	}
+	index::write_index(out_base, index)
+}
+
+/// Fetches and processes a single version for debugging, writing only its component and leaving
+/// the index untouched.
+pub async fn regen(
+	client: &reqwest::Client,
+	config: &Config,
+	id: &str,
+) -> Result<helix::component::Component> {
+	let upstream_base = &config.paths.upstream_root.join("mojang");
+	let version_base = &upstream_base.join("versions");
+	fs::create_dir_all(version_base)?;
+	let out_base = &config.paths.out_root.join("net.minecraft");
+	fs::create_dir_all(out_base)?;
 
-	index.sort_by(|x, y| y.release_time.cmp(&x.release_time));
+	let version = fetch_manifest(client, config)
+		.await?
+		.versions
+		.into_iter()
+		.find(|version| version.id == id)
+		.with_context(|| format!("{id} is not in the version manifest"))?;
+	let path = fetch_version(client, version_base, version).await?;
+	if config.dev_downloads {
+		fs::create_dir_all(upstream_base.join("artifacts"))?;
+		fetch_dev_libraries(client, upstream_base, &path).await?;
+	}
 
-	fs::write(
-		out_base.join("index.json"),
-		serde_json::to_string_pretty(&index)?,
+	let mut component = process_version(
+		&path,
+		&load_overrides(&config.paths.overrides)?,
+		&load_placeholders(&config.paths.placeholders)?,
 	)?;
+	if config.dev_downloads {
+		add_dev_downloads(&mut component, upstream_base)?;
+	}
+	index::write_component(out_base, &component)?;
+	Ok(component)
+}
 
-	Ok(())
+/// A replacement download for a library, keyed by its coordinate in `overrides.json`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LibraryOverride {
+	url: String,
+	sha1: String,
+	size: u32,
+}
+
+pub type Overrides = HashMap<GradleSpecifier, LibraryOverride>;
+
+pub fn load_overrides(path: &Path) -> Result<Overrides> {
+	let content =
+		fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+	let overrides: Overrides = serde_json::from_str(&content)
+		.with_context(|| format!("Failed to parse {}", path.display()))?;
+	for (name, replacement) in &overrides {
+		ensure!(
+			replacement.sha1.len() == 40 && HEXLOWER.decode(replacement.sha1.as_bytes()).is_ok(),
+			"Override for {name} has an invalid SHA-1"
+		);
+	}
+	Ok(overrides)
+}
+
+/// Some versions use another version of an LWJGL library on some OSes, which doesn't fit on a
+/// single classpath. For every LWJGL library listed in more than one version, this returns whether
+/// each of them is the version allowed on the most platforms, which is used everywhere instead.
+fn pick_lwjgl_versions(libraries: &[MojangLibrary]) -> Result<HashMap<GradleSpecifier, bool>> {
+	// group, artifact and classifier -> the versions of that library, with how many platforms
+	// each is allowed on
+	let mut artifacts: IndexMap<_, Vec<(&GradleSpecifier, usize)>> = IndexMap::new();
+	for library in libraries
+		.iter()
+		.filter(|library| library.name.group.starts_with("org.lwjgl"))
+	{
+		let name = &library.name;
+		let platform_count = if library.rules.is_empty() {
+			usize::MAX
+		} else {
+			rules::evaluate_rules_platforms(&library.rules)
+				.with_context(|| format!("Rules for \"{name}\" failed to evaluate"))?
+				.iter()
+				.map(|platform| platform.os.len())
+				.sum()
+		};
+		artifacts
+			.entry((&name.group, &name.artifact, &name.classifier))
+			.or_default()
+			.push((name, platform_count));
+	}
+
+	let mut picked = HashMap::new();
+	for versions in artifacts.into_values() {
+		let first_version = &versions[0].0.version;
+		if versions
+			.iter()
+			.all(|(name, _)| &name.version == first_version)
+		{
+			continue;
+		}
+		let most = versions.iter().map(|&(_, count)| count).max().unwrap();
+		// on a tie, the version listed first wins
+		let kept = &versions
+			.iter()
+			.find(|&&(_, count)| count == most)
+			.unwrap()
+			.0
+			.version;
+		for (name, _) in &versions {
+			picked.insert((*name).clone(), &name.version == kept);
+		}
+	}
+	Ok(picked)
 }
 
 pub fn process_version(
-	file: &fs::DirEntry,
-	out_base: &Path,
+	path: &Path,
+	overrides: &Overrides,
+	placeholders: &Placeholders,
 ) -> Result<helix::component::Component> {
-	let mut version: MojangVersion = serde_json::from_str(&fs::read_to_string(file.path())?)
-		.with_context(|| format!("Failed to parse {}", file.file_name().to_str().unwrap()))?;
-	ensure!(version.inherits_from.is_none());
+	let mut version = read_version(path)?;
+	// parents are looked up next to the child, and merged one level at a time, so that a child
+	// of a child sees the fields of every ancestor
+	let mut ancestors = HashSet::from([version.id.clone()]);
+	while let Some(parent_id) = version.inherits_from.clone() {
+		ensure!(
+			ancestors.insert(parent_id.clone()),
+			"{} inherits from itself through {parent_id}",
+			version.id
+		);
+		let parent = read_version(&path.with_file_name(format!("{parent_id}.json")))?;
+		version = merge_parent(version, parent);
+	}
 
 	let mut classpath = IndexSet::with_capacity(version.libraries.len());
 	let mut natives = IndexSet::with_capacity(version.libraries.len());
 	let mut downloads = IndexMap::with_capacity(version.libraries.len() * 2);
-	let game_download = &version
+	// downloads of an artifact that is already in `downloads` with another size or hash; they are
+	// kept, so that Component::validate reports all of them together
+	let mut conflicting_downloads = vec![];
+	let version_downloads = version
 		.downloads
 		.as_ref()
-		.with_context(|| "Download missing")?
-		.client;
+		.with_context(|| "Download missing")?;
+	for key in version_downloads.unknown.keys() {
+		eprintln!(
+			"Warning: ignoring unknown download {key} of version {}",
+			version.id
+		);
+	}
+	let game_download = &version_downloads.client;
 	let game_artifact_name = GradleSpecifier {
 		group: "com.mojang".to_owned(),
 		artifact: "minecraft".to_owned(),
@@ -364,18 +966,22 @@ pub fn process_version(
 			name: game_artifact_name.to_owned(),
 			url: game_download.url.to_string(),
 			size: game_download.size,
-			hash: helix::component::Hash::SHA1(game_download.sha1.to_string()),
+			hash: helix::component::Hash::parse_sha1(&game_download.sha1)?,
 		},
 	);
 	let mut traits = BTreeSet::new();
+	// versions before 1.6 use one of these two indexes, which map to resources by path rather
+	// than by hash; versions without an index at all are older still
+	if version
+		.asset_index
+		.as_ref()
+		.is_none_or(|assets| assets.id == "pre-1.6" || assets.id == "legacy")
+	{
+		traits.insert(helix::component::Trait::LegacyAssets);
+	}
+	let lwjgl_versions = pick_lwjgl_versions(&version.libraries)?;
 	let mut is_lwjgl3 = false;
 	for library in &mut version.libraries {
-		let mut ignore_rules = false;
-		ensure!(
-			library.rules.len() <= 1
-				|| (library.rules[0].is_always_allow() && library.rules.len() <= 2),
-			"Multiple rules not handled currently"
-		);
 		if library.name.artifact.contains("log4j") {
 			lazy_static! {
 				static ref OLDEST_UPGRADE_VERSION: Maven3ArtifactVersion<'static> =
@@ -383,7 +989,8 @@ pub fn process_version(
 				static ref NEWEST_UPGRADE_VERSION: Maven3ArtifactVersion<'static> =
 					Maven3ArtifactVersion::new("2.17.0");
 			}
-			let parsed_version = Maven3ArtifactVersion::new(&library.name.version);
+			let parsed_version = parse_maven_version(&library.name.version)
+				.with_context(|| format!("Cannot check {} for the log4j upgrade", library.name))?;
 			let mut changed_log4j = false;
 			if *OLDEST_UPGRADE_VERSION <= parsed_version && parsed_version < *NEWEST_UPGRADE_VERSION
 			{
@@ -394,111 +1001,90 @@ pub fn process_version(
 			{
 				changed_log4j = true;
 			}
-			let log4j_url = |maven: &str, module: &str, version: &str| {
-				format!("https://{maven}/org/apache/logging/log4j/{module}/{version}/{module}-{version}.jar")
-			};
-			if changed_log4j {
-				if let Some(artifact) = &mut library.downloads.artifact {
-					artifact.url = log4j_url(
-						if library.name.version == "2.17.0" {
-							"libraries.minecraft.net"
-						} else {
-							"files.helixlauncher.dev/maven"
-						},
-						&library.name.artifact,
-						&library.name.version,
-					);
-					(artifact.sha1, artifact.size) =
-						match (&*library.name.artifact, &*library.name.version) {
-							("log4j-core", "2.17.0") => (
-								String::from("fe6e7a32c1228884b9691a744f953a55d0dd8ead"),
-								1789339,
-							),
-							("log4j-slf4j18-impl", "2.17.0") => (
-								String::from("bd7f6c0b9224dd214afb4e684957e2349b529a8d"),
-								21244,
-							),
-							("log4j-api", "2.17.0") => (
-								String::from("bbd791e9c8c9421e45337c4fe0a10851c086e36c"),
-								301776,
-							),
-							("log4j-core", "2.0-beta9") => (
-								String::from("db59ef51488f7ea6a2fd1a0bd8d862cf95f02b7a"),
-								677741,
-							),
-							("log4j-core", "2.0-rc2") => (
-								String::from("4ffd3e05eebaf965199d0b54d3cd8f8e342c9c08"),
-								765649,
-							),
-							_ => todo!("{}", library.name),
-						}
-				}
-			}
+			ensure!(
+				!changed_log4j || overrides.contains_key(&library.name),
+				"No override for {}",
+				library.name
+			);
 		}
-		if library.name.group.starts_with("org.lwjgl") {
-			if library.name.version.starts_with("3.") {
-				is_lwjgl3 = true;
-			}
-
-			// skip any LWJGL library specific to one OS (this might be too generic, but is fine
-			// for everything currently existing)
-
-			if library.rules.len() == 2
-				&& library.rules[0].is_always_allow()
-				&& matches!(library.rules[1].action, RuleAction::Disallow)
-				&& matches!(&library.rules[1].os, Some(os) if os.name.is_some())
-			{
-				ignore_rules = true;
-			}
-
-			if library.rules.len() == 1
-				&& matches!(library.rules[0].action, RuleAction::Allow)
-				&& matches!(&library.rules[0].os, Some(os) if os.name.is_some())
-				&& !matches!(&library.name.classifier, Some(classifier) if classifier.contains("natives"))
-			{
-				continue;
+		if let Some(replacement) = overrides.get(&library.name) {
+			if let Some(artifact) = &mut library.downloads.artifact {
+				artifact.url = replacement.url.to_owned();
+				artifact.sha1 = replacement.sha1.to_owned();
+				artifact.size = replacement.size;
 			}
 		}
+		if library.name.group.starts_with("org.lwjgl") && library.name.version.starts_with("3.") {
+			is_lwjgl3 = true;
+		}
 
-		let platform = if ignore_rules || library.rules.is_empty() {
-			None
-		} else {
-			Some(helix::component::Platform {
-				os: rules::evaluate_rules_os_name(&library.rules).with_context(|| {
-					format!("Rules for \"{}\" failed to evaluate", library.name)
-				})?,
-				arch: None,
-			})
+		let platforms = match lwjgl_versions.get(&library.name) {
+			Some(true) => None,
+			Some(false) => continue,
+			None if library.rules.is_empty() => None,
+			None => {
+				let mut platforms =
+					rules::evaluate_rules_platforms(&library.rules).with_context(|| {
+						format!("Rules for \"{}\" failed to evaluate", library.name)
+					})?;
+				if platforms.is_empty() {
+					continue;
+				}
+				// LWJGL 3 natives are classpath jars, one per OS and architecture, whose rules
+				// only check the OS
+				if let Some(classifier) = &library.name.classifier {
+					if let Some(arch) = rules::natives_classifier_arch(classifier)
+						.with_context(|| format!("Unknown natives in {}", library.name))?
+					{
+						for platform in &mut platforms {
+							platform.arch.get_or_insert(arch);
+						}
+					}
+				}
+				Some(platforms)
+			}
 		};
 
 		let mut add_download = |name: &GradleSpecifier, artifact: &MojangLibraryArtifact| {
-			if downloads.contains_key(name) {
-				ensure!(
-					matches!(&downloads[name].hash, helix::component::Hash::SHA1(sha1) if *sha1 == artifact.sha1)
-				);
-			} else {
-				downloads.insert(
-					name.to_owned(),
-					helix::component::Download {
-						name: name.to_owned(),
-						url: artifact.url.to_owned(),
-						size: artifact.size,
-						hash: helix::component::Hash::SHA1(artifact.sha1.to_owned()),
-					},
-				);
+			let download = helix::component::Download {
+				name: name.to_owned(),
+				url: artifact.url.to_owned(),
+				size: artifact.size,
+				hash: helix::component::Hash::parse_sha1(&artifact.sha1)?,
+			};
+			match downloads.get(name) {
+				None => {
+					downloads.insert(name.to_owned(), download);
+				}
+				Some(existing)
+					if existing.hash != download.hash || existing.size != download.size =>
+				{
+					conflicting_downloads.push(download)
+				}
+				Some(_) => {}
 			}
-			Ok(())
+			anyhow::Ok(())
 		};
 
 		if let Some(artifact) = &library.downloads.artifact {
 			add_download(&library.name, artifact)?;
-			classpath.insert(match &platform {
-				None => helix::component::ConditionalClasspathEntry::All(library.name.to_owned()),
-				Some(platform) => helix::component::ConditionalClasspathEntry::PlatformSpecific {
-					name: library.name.to_owned(),
-					platform: platform.clone(),
-				},
-			});
+			match &platforms {
+				None => {
+					classpath.insert(helix::component::ConditionalClasspathEntry::All(
+						library.name.to_owned(),
+					));
+				}
+				Some(platforms) => {
+					for platform in platforms {
+						classpath.insert(
+							helix::component::ConditionalClasspathEntry::PlatformSpecific {
+								name: library.name.to_owned(),
+								platform: platform.clone(),
+							},
+						);
+					}
+				}
+			}
 		}
 
 		for (os, classifier) in &library.natives {
@@ -520,18 +1106,32 @@ pub fn process_version(
 								format!("{classifier} on {} does not exist", library.name)
 							})?,
 					)?;
+					// an os.version rule of the library gates its natives on that OS as well
+					let os_version = platforms.iter().flatten().find_map(|platform| {
+						platform
+							.os
+							.contains(&os)
+							.then(|| platform.os_version.clone())
+							.flatten()
+					});
 					natives.insert(helix::component::Native {
 						name,
-						platform: helix::component::Platform { os: vec![os], arch },
+						platform: helix::component::Platform {
+							os: vec![os],
+							arch,
+							os_version,
+						},
 						exclusions: library.extract.exclude.clone(),
 					});
 					Ok(())
 				};
-			if platform
+			if platforms
 				.as_ref()
-				.map_or(true, |platform| platform.os.contains(os))
+				.is_none_or(|platforms| platforms.iter().any(|platform| platform.os.contains(os)))
 			{
 				if classifier.contains("${arch}") {
+					// ${arch} is the bitness of an x86 JVM, so it never stands for ARM or
+					// RISC-V natives
 					process_native(
 						*os,
 						&classifier.replace("${arch}", "32"),
@@ -549,45 +1149,31 @@ pub fn process_version(
 		}
 	}
 
+	let mut platform_traits = vec![];
 	if is_lwjgl3 {
-		traits.insert(helix::component::Trait::MacStartOnFirstThread);
-	}
-
-	fn remap_vars<'a>(s: &'a str, version: &MojangVersion) -> Cow<'a, str> {
-		lazy_static! {
-			static ref VAR_PATTERN: Regex = Regex::new("(\\$\\{[a-zA-Z0-9_]+\\})").unwrap();
-		}
-		VAR_PATTERN.replace_all(s, |c: &Captures<'_>| match c.get(1).unwrap().as_str() {
-			"${auth_access_token}" => "${user.token}",
-			"${auth_player_name}" => "${user.name}",
-			"${version_name}" => "${instance.minecraft_version}",
-			"${game_directory}" => "${instance.game_dir}",
-			"${assets_root}" => "${instance.assets_dir}",
-			"${assets_index_name}" => "${instance.assets_index_name}",
-			"${auth_uuid}" => "${user.uuid}",
-			"${clientid}" => "",                  // TODO
-			"${auth_xuid}" => "",                 // TODO
-			"${auth_session}" => "${user.token}", // TODO: is this really just the token?
-			"${user_type}" => "${user.type}",     // TODO: what is this?
-			"${version_type}" => version.version_type.as_str(),
-			"${resolution_width}" => "${window.width}",
-			"${resolution_height}" => "${window.height}",
-			"${user_properties}" => "{}", // was used for twitch integration which is no longer a thing
-			"${game_assets}" => "${instance.virtual_assets_dir}",
-			"${quickPlaySingleplayer}" => "${launch.world}",
-			"${quickPlayMultiplayer}" => "${launch.server}",
-			_ => panic!("{} not supported", s),
-		})
+		platform_traits.push(helix::component::PlatformTrait {
+			kind: helix::component::Trait::MacStartOnFirstThread,
+			platform: helix::component::Platform {
+				os: vec![OsName::Osx],
+				arch: None,
+				os_version: None,
+			},
+		});
 	}
 
-	// TODO: add traits from arguments
+	let remap = |s: &str| -> Result<String> {
+		Ok(remap_vars(s, &version, placeholders)
+			.with_context(|| format!("Failed to map the arguments of {}", version.id))?
+			.into_owned())
+	};
+
 	let mut arguments = Vec::new();
 	if let Some(version_arguments) = &version.arguments {
 		for argument in &version_arguments.game {
 			match argument {
-				MojangConditionalValue::Always(argument) => arguments.push(
-					MinecraftArgument::Always(remap_vars(argument, &version).into()),
-				),
+				MojangConditionalValue::Always(argument) => {
+					arguments.push(MinecraftArgument::Always(remap(argument)?))
+				}
 				MojangConditionalValue::Conditional { rules, value } => {
 					ensure!(rules.len() == 1);
 					ensure!(rules[0].action == RuleAction::Allow);
@@ -600,7 +1186,6 @@ pub fn process_version(
 						}
 						if let Some(has_custom_resolution) = features.has_custom_resolution {
 							ensure!(has_custom_resolution && feature.is_none());
-							traits.insert(helix::component::Trait::SupportsCustomResolution);
 							feature = Some(ConditionFeature::CustomResolution);
 						}
 						if let Some(has_quick_plays_support) = features.has_quick_plays_support {
@@ -611,13 +1196,11 @@ pub fn process_version(
 							features.is_quick_play_singleplayer
 						{
 							ensure!(is_quick_play_singleplayer && feature.is_none());
-							traits.insert(helix::component::Trait::SupportsQuickPlayWorld);
 							feature = Some(ConditionFeature::QuickPlayWorld);
 						}
 						if let Some(is_quick_play_multiplayer) = features.is_quick_play_multiplayer
 						{
 							ensure!(is_quick_play_multiplayer && feature.is_none());
-							traits.insert(helix::component::Trait::SupportsQuickPlayServer);
 							feature = Some(ConditionFeature::QuickPlayServer);
 						}
 						if let Some(is_quick_play_realms) = features.is_quick_play_realms {
@@ -630,7 +1213,7 @@ pub fn process_version(
 					let feature = feature.unwrap();
 					for argument in value {
 						arguments.push(MinecraftArgument::Conditional {
-							value: remap_vars(argument, &version).into(),
+							value: remap(argument)?,
 							feature,
 						})
 					}
@@ -640,9 +1223,7 @@ pub fn process_version(
 	}
 	if let Some(minecraft_arguments) = &version.minecraft_arguments {
 		for argument in minecraft_arguments.split(' ') {
-			arguments.push(MinecraftArgument::Always(
-				remap_vars(argument, &version).into(),
-			));
+			arguments.push(MinecraftArgument::Always(remap(argument)?));
 		}
 		arguments.push(MinecraftArgument::Conditional {
 			value: String::from("--demo"),
@@ -651,26 +1232,513 @@ pub fn process_version(
 		// TODO: which conditional arguments does mojang launcher add automatically?
 	}
 
+	let logging = match version
+		.logging
+		.as_ref()
+		.and_then(|logging| logging.client.as_ref())
+	{
+		Some(client) => {
+			ensure!(
+				client.kind == "log4j2-xml",
+				"Unsupported logging config type {}",
+				client.kind
+			);
+			Some(helix::component::Logging {
+				id: client.file.id.to_owned(),
+				url: client.file.url.to_owned(),
+				sha1: client.file.sha1.to_owned(),
+				size: client.file.size,
+				argument: remap(&client.argument)?,
+			})
+		}
+		None => None,
+	};
+
+	let mut jvm_arguments = Vec::new();
+	if let Some(version_arguments) = &version.arguments {
+		for argument in &version_arguments.jvm {
+			match argument {
+				MojangConditionalValue::Always(argument) => {
+					jvm_arguments.push(MinecraftArgument::Always(remap(argument)?))
+				}
+				MojangConditionalValue::Conditional { rules, value } => {
+					ensure!(rules.len() == 1);
+					ensure!(rules[0].action == RuleAction::Allow);
+					ensure!(rules[0].features.is_none());
+					let os = rules[0]
+						.os
+						.as_ref()
+						.with_context(|| "JVM argument rules empty")?;
+					let platform = helix::component::Platform {
+						os: os.name.into_iter().collect(),
+						arch: os.arch.as_deref().map(rules::parse_arch).transpose()?,
+						os_version: os.version.clone(),
+					};
+					for argument in value {
+						jvm_arguments.push(MinecraftArgument::PlatformSpecific {
+							value: remap(argument)?,
+							platform: platform.clone(),
+						});
+					}
+				}
+			}
+		}
+	} else {
+		// versions from before the arguments format leave the JVM arguments to the launcher;
+		// these are the ones the vanilla launcher uses for them
+		for argument in [
+			"-Djava.library.path=${natives_directory}",
+			"-cp",
+			"${classpath}",
+		] {
+			jvm_arguments.push(MinecraftArgument::Always(remap(argument)?));
+		}
+	}
+
+	// derived from the emitted arguments rather than from the rules above, so that the traits
+	// stay correct no matter which manifest shape produced an argument
+	for argument in &arguments {
+		let feature = match argument {
+			MinecraftArgument::Conditional { feature, .. } => *feature,
+			MinecraftArgument::PlatformSpecific { .. } => continue,
+			MinecraftArgument::Always(value) => match value.as_str() {
+				"--quickPlaySingleplayer" => ConditionFeature::QuickPlayWorld,
+				"--quickPlayMultiplayer" => ConditionFeature::QuickPlayServer,
+				_ => continue,
+			},
+		};
+		traits.insert(match feature {
+			ConditionFeature::Demo => continue,
+			ConditionFeature::Fullscreen => continue,
+			ConditionFeature::CustomResolution => helix::component::Trait::SupportsCustomResolution,
+			ConditionFeature::QuickPlayServerLegacy => {
+				helix::component::Trait::SupportsQuickPlayServerLegacy
+			}
+			ConditionFeature::QuickPlayServer => helix::component::Trait::SupportsQuickPlayServer,
+			ConditionFeature::QuickPlayWorld => helix::component::Trait::SupportsQuickPlayWorld,
+		});
+	}
+
 	let component = helix::component::Component {
-		format_version: 1,
+		format_version: helix::component::FORMAT_VERSION,
 		id: "net.minecraft".into(),
 		traits,
+		platform_traits,
 		assets: version.asset_index.map(|a| a.into()),
+		logging,
 		version: version.id.to_owned(),
-		requires: vec![], // TODO: lwjgl 2 (deal with that later)
-		conflicts: vec![],
-		downloads: downloads.into_values().collect(),
+		channel: Some(version.version_type.as_str().into()),
+		dependencies: helix::component::Dependencies {
+			// LWJGL (2 and 3 alike) is kept inline instead of being split into an org.lwjgl
+			// component: each version pins its own builds, sometimes patched by Mojang, its
+			// natives are handled together with the other natives, and nothing needs to swap it
+			// out independently of the game
+			requires: vec![],
+			..Default::default()
+		},
+		downloads: downloads
+			.into_values()
+			.chain(conflicting_downloads)
+			.collect(),
 		classpath: classpath.into_iter().collect(),
 		natives: natives.into_iter().collect(),
 		game_arguments: arguments,
+		jvm_arguments,
+		tweakers: vec![],
 		main_class: Some(version.main_class),
+		min_java_version: version.java_version.as_ref().map(|java| java.major_version),
+		java_component: version.java_version.map(|java| java.component),
+		dev_downloads: vec![],
 		jarmods: vec![],
 		game_jar: Some(game_artifact_name),
+		java_runtimes: vec![],
+		installer: None,
 		release_time: version.release_time,
 	};
-	fs::write(
-		out_base.join(format!("{}.json", version.id)),
-		serde_json::to_string_pretty(&component)?,
-	)?;
+	component
+		.validate()
+		.with_context(|| format!("Version {} is invalid", component.version))?;
 	Ok(component)
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use std::path::PathBuf;
+
+	use helix::component::{Arch, ConditionalClasspathEntry, Platform, Trait};
+
+	use super::*;
+
+	fn fixture(name: &str) -> PathBuf {
+		[env!("CARGO_MANIFEST_DIR"), "tests/fixtures/mojang", name]
+			.iter()
+			.collect()
+	}
+
+	pub(crate) fn process_fixture(name: &str) -> helix::component::Component {
+		process_version(&fixture(name), &Overrides::new(), &builtin_placeholders()).unwrap()
+	}
+
+	fn classpath_platform(component: &helix::component::Component, name: &str) -> Vec<Platform> {
+		let name: GradleSpecifier = name.parse().unwrap();
+		component
+			.classpath
+			.iter()
+			.filter_map(|entry| match entry {
+				ConditionalClasspathEntry::PlatformSpecific {
+					name: entry_name,
+					platform,
+				} if *entry_name == name => Some(platform.clone()),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn unknown_downloads_are_ignored() {
+		let component = process_fixture("unknown-download.json");
+		let names: Vec<_> = component
+			.downloads
+			.iter()
+			.map(|download| download.name.to_string())
+			.collect();
+		assert_eq!(names, ["com.mojang:minecraft:1.2.5:client"]);
+	}
+
+	#[test]
+	fn natives_classifier_arch() {
+		for (classifier, arch) in [
+			("natives-macos", Some(Arch::X86_64)),
+			("natives-macos-arm64", Some(Arch::Arm64)),
+			("natives-windows-x86", Some(Arch::X86)),
+			("natives-linux-arm32", Some(Arch::Arm32)),
+			("natives-linux-riscv64", Some(Arch::Riscv64)),
+			("sources", None),
+		] {
+			assert_eq!(
+				rules::natives_classifier_arch(classifier).unwrap(),
+				arch,
+				"{classifier}"
+			);
+		}
+		assert!(rules::natives_classifier_arch("natives-linux-sparc").is_err());
+	}
+
+	#[test]
+	fn lwjgl3_natives_are_arch_specific() {
+		let component = process_fixture("1.19.json");
+		for (classifier, os, arch) in [
+			("natives-linux", OsName::Linux, Arch::X86_64),
+			("natives-macos", OsName::Osx, Arch::X86_64),
+			("natives-macos-arm64", OsName::Osx, Arch::Arm64),
+			("natives-windows", OsName::Windows, Arch::X86_64),
+			("natives-windows-arm64", OsName::Windows, Arch::Arm64),
+			("natives-windows-x86", OsName::Windows, Arch::X86),
+		] {
+			assert_eq!(
+				classpath_platform(
+					&component,
+					&format!("org.lwjgl:lwjgl-glfw:3.3.1:{classifier}")
+				),
+				[Platform {
+					os: vec![os],
+					arch: Some(arch),
+					os_version: None,
+				}],
+				"{classifier}"
+			);
+		}
+		// not a natives jar, so it applies to every architecture
+		assert_eq!(
+			classpath_platform(&component, "ca.weblite:java-objc-bridge:1.1"),
+			[Platform {
+				os: vec![OsName::Osx],
+				arch: None,
+				os_version: None,
+			}]
+		);
+	}
+
+	#[test]
+	fn processing_is_deterministic() {
+		// each run gets its own placeholder map, so a HashMap order leaking into the output would
+		// show up as a difference
+		let first = serde_json::to_vec_pretty(&process_fixture("1.19.json")).unwrap();
+		let second = serde_json::to_vec_pretty(&process_fixture("1.19.json")).unwrap();
+		assert_eq!(first, second);
+	}
+
+	fn argument_values(arguments: &[MinecraftArgument]) -> Vec<&str> {
+		arguments
+			.iter()
+			.map(|argument| match argument {
+				MinecraftArgument::Always(value)
+				| MinecraftArgument::Conditional { value, .. }
+				| MinecraftArgument::PlatformSpecific { value, .. } => value.as_str(),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn inheritance_is_resolved_transitively() {
+		let component = process_fixture("inherit/child.json");
+		assert_eq!(component.version, "child");
+		assert_eq!(
+			component.main_class.as_deref(),
+			Some("org.example.ChildMain")
+		);
+
+		// the child's libraries come first, and asm, which the parent lists too, only once
+		let classpath: Vec<_> = component
+			.classpath
+			.iter()
+			.map(|entry| match entry {
+				ConditionalClasspathEntry::All(name) => name.to_string(),
+				ConditionalClasspathEntry::PlatformSpecific { name, .. } => {
+					panic!("{name} is platform specific")
+				}
+			})
+			.collect();
+		assert_eq!(
+			classpath,
+			[
+				"org.example:child-library:1.0",
+				"org.ow2.asm:asm:9.3",
+				"com.mojang:brigadier:1.0.18",
+				"com.google.guava:guava:31.0.1-jre",
+			]
+		);
+
+		// arguments are concatenated from the grandparent down
+		assert_eq!(
+			argument_values(&component.game_arguments),
+			[
+				"--username",
+				"${user.name}",
+				"--version",
+				"${instance.minecraft_version}",
+				"--parentArgument",
+				"--childArgument",
+			]
+		);
+		assert_eq!(
+			argument_values(&component.jvm_arguments),
+			[
+				"-Djava.library.path=${instance.natives_dir}",
+				"-cp",
+				"${launch.classpath}",
+				"-Dparent=true",
+			]
+		);
+
+		// only the grandparent has a javaVersion
+		assert_eq!(component.min_java_version, Some(17));
+		assert_eq!(
+			component.java_component.as_deref(),
+			Some("java-runtime-gamma")
+		);
+	}
+
+	#[test]
+	fn inheritance_cycles_are_rejected() {
+		let error = process_version(
+			&fixture("inherit-cycle/a.json"),
+			&Overrides::new(),
+			&builtin_placeholders(),
+		)
+		.unwrap_err();
+		assert!(
+			format!("{error:#}").contains("a inherits from itself through a"),
+			"{error:#}"
+		);
+	}
+
+	fn rules(rules: serde_json::Value) -> Vec<Rule> {
+		serde_json::from_value(rules).unwrap()
+	}
+
+	fn platform(os: &[OsName], arch: Option<Arch>, os_version: Option<&str>) -> Platform {
+		Platform {
+			os: os.to_vec(),
+			arch,
+			os_version: os_version.map(str::to_owned),
+		}
+	}
+
+	#[test]
+	fn arm64_only_natives_get_the_arch() {
+		let rules = rules(serde_json::json!([
+			{ "action": "allow", "os": { "name": "osx", "arch": "arm64" } },
+		]));
+		assert_eq!(
+			rules::evaluate_rules_platforms(&rules).unwrap(),
+			[platform(&[OsName::Osx], Some(Arch::Arm64), None)]
+		);
+	}
+
+	#[test]
+	fn disallowing_x86_allows_every_other_arch() {
+		let rules = rules(serde_json::json!([
+			{ "action": "allow" },
+			{ "action": "disallow", "os": { "arch": "x86" } },
+		]));
+		let all_os = [OsName::Linux, OsName::Osx, OsName::Windows];
+		assert_eq!(
+			rules::evaluate_rules_platforms(&rules).unwrap(),
+			[Arch::X86_64, Arch::Arm32, Arch::Arm64, Arch::Riscv64].map(|arch| platform(
+				&all_os,
+				Some(arch),
+				None
+			))
+		);
+	}
+
+	#[test]
+	fn three_rules_with_an_os_version() {
+		let rules = rules(serde_json::json!([
+			{ "action": "allow" },
+			{ "action": "disallow", "os": { "name": "osx" } },
+			{ "action": "allow", "os": { "name": "osx", "version": "^10\\.5\\.\\d$" } },
+		]));
+		assert_eq!(
+			rules::evaluate_rules_platforms(&rules).unwrap(),
+			[
+				platform(&[OsName::Linux, OsName::Windows], None, None),
+				platform(&[OsName::Osx], None, Some("^10\\.5\\.\\d$")),
+			]
+		);
+	}
+
+	#[test]
+	fn three_rules_with_an_arch() {
+		let rules = rules(serde_json::json!([
+			{ "action": "allow" },
+			{ "action": "disallow", "os": { "name": "osx" } },
+			{ "action": "allow", "os": { "name": "osx", "arch": "arm64" } },
+		]));
+		let not_osx = [OsName::Linux, OsName::Windows];
+		assert_eq!(
+			rules::evaluate_rules_platforms(&rules).unwrap(),
+			[
+				platform(&not_osx, Some(Arch::X86), None),
+				platform(&not_osx, Some(Arch::X86_64), None),
+				platform(&not_osx, Some(Arch::Arm32), None),
+				platform(
+					&[OsName::Linux, OsName::Osx, OsName::Windows],
+					Some(Arch::Arm64),
+					None
+				),
+				platform(&not_osx, Some(Arch::Riscv64), None),
+			]
+		);
+	}
+
+	#[test]
+	fn three_rules_disallowing_an_allowed_os() {
+		let rules = rules(serde_json::json!([
+			{ "action": "allow", "os": { "name": "linux" } },
+			{ "action": "allow", "os": { "name": "windows" } },
+			{ "action": "disallow", "os": { "name": "linux" } },
+		]));
+		assert_eq!(
+			rules::evaluate_rules_platforms(&rules).unwrap(),
+			[platform(&[OsName::Windows], None, None)]
+		);
+	}
+
+	#[test]
+	fn version_gated_natives_keep_the_os_version() {
+		let component = process_fixture("version-gated-natives.json");
+		let platforms: Vec<_> = component
+			.natives
+			.iter()
+			.map(|native| {
+				(
+					native.name.classifier.as_deref().unwrap(),
+					native.platform.clone(),
+				)
+			})
+			.collect();
+		let windows_6 = |arch| Platform {
+			os: vec![OsName::Windows],
+			arch: Some(arch),
+			os_version: Some("^6\\.".into()),
+		};
+		assert_eq!(
+			platforms,
+			[
+				("natives-linux", platform(&[OsName::Linux], None, None)),
+				("natives-osx", platform(&[OsName::Osx], None, None)),
+				("natives-windows", platform(&[OsName::Windows], None, None)),
+				("natives-windows-32", windows_6(Arch::X86)),
+				("natives-windows-64", windows_6(Arch::X86_64)),
+			]
+		);
+	}
+
+	#[test]
+	fn unusual_maven_versions() {
+		let version = |version| parse_maven_version(version).unwrap();
+		assert!(version("2.0-beta9") < version("2.8.0"));
+		assert!(version("2.17.0") < version("2.17.1-rc1"));
+		assert!(version("2.17.1-rc1") < version("2.17.1"));
+		for version in ["latest", "2.17.1+build.5", "v2.17.1", "2.17.1-", ""] {
+			assert!(parse_maven_version(version).is_err(), "{version}");
+		}
+	}
+
+	#[test]
+	fn java_version_is_carried_over() {
+		let component = process_fixture("1.19.json");
+		assert_eq!(component.min_java_version, Some(17));
+		assert_eq!(
+			component.java_component.as_deref(),
+			Some("java-runtime-gamma")
+		);
+
+		// from before Mojang added javaVersion to the manifests
+		let component = process_fixture("version-gated-natives.json");
+		assert_eq!(component.min_java_version, None);
+		assert_eq!(component.java_component, None);
+	}
+
+	#[test]
+	fn logging_config_is_carried_over() {
+		let logging = process_fixture("1.19.json").logging.unwrap();
+		assert_eq!(logging.id, "client-1.12.xml");
+		assert_eq!(
+			logging.url,
+			"https://piston-data.mojang.com/v1/objects/7babc233de26ab19ead1b9c278128d5c434910ee/client-1.12.xml"
+		);
+		assert_eq!(logging.sha1, "7babc233de26ab19ead1b9c278128d5c434910ee");
+		assert_eq!(logging.size, 888);
+		assert_eq!(
+			logging.argument,
+			"-Dlog4j.configurationFile=${launch.logging_config}"
+		);
+	}
+
+	#[test]
+	fn quick_play_traits_follow_the_arguments() {
+		let component = process_fixture("1.20.json");
+		assert_eq!(
+			component.traits,
+			BTreeSet::from([
+				Trait::SupportsCustomResolution,
+				Trait::SupportsQuickPlayServer,
+				Trait::SupportsQuickPlayWorld,
+			])
+		);
+		// the quick play path and realms have no Helix equivalent, so they are left out
+		assert!(!argument_values(&component.game_arguments)
+			.iter()
+			.any(|value| value.starts_with("--quickPlayPath")
+				|| value.starts_with("--quickPlayRealms")));
+
+		// 1.19 has no quick play arguments
+		assert_eq!(
+			process_fixture("1.19.json").traits,
+			BTreeSet::from([Trait::SupportsCustomResolution])
+		);
+	}
+}
@@ -0,0 +1,121 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use futures::{StreamExt, TryStreamExt};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use helixlauncher_meta::util::GradleSpecifier;
+
+use crate::{
+	config::Config,
+	fetch_cached,
+	forge::{self, Flavor},
+	report::Failures,
+	with_retry,
+};
+
+const MAVEN_URL: &str = "https://maven.neoforged.net/releases/";
+
+/// NeoForge for 1.20.2 and up. The builds for 1.20.1 were published as `net.neoforged:forge`
+/// with Forge's version scheme, and are not handled.
+pub const NEOFORGE: Flavor = Flavor {
+	id: "net.neoforged.neoforge",
+	upstream: "neoforge",
+	group: "net.neoforged",
+	artifact: "neoforge",
+	maven_url: MAVEN_URL,
+	versions: neoforge_versions,
+};
+
+/// NeoForge versions are named `neoforge-20.4.190`, and its artifacts `20.4.190`.
+fn neoforge_versions(id: &str, _minecraft_version: &str) -> Result<(String, String)> {
+	let version = id
+		.strip_prefix("neoforge-")
+		.with_context(|| format!("Could not extract NeoForge version from {id}"))?;
+	Ok((version.to_owned(), version.to_owned()))
+}
+
+fn installer(version: &str) -> GradleSpecifier {
+	GradleSpecifier {
+		group: NEOFORGE.group.into(),
+		artifact: NEOFORGE.artifact.into(),
+		version: version.into(),
+		classifier: Some("installer".into()),
+		extension: "jar".into(),
+	}
+}
+
+/// Downloads the installer of every NeoForge version listed in the Maven metadata.
+pub async fn fetch(client: &reqwest::Client, config: &Config, failures: &Failures) -> Result<()> {
+	let upstream_base = config.paths.upstream_root.join(NEOFORGE.upstream);
+	let installer_base = &upstream_base.join("installers");
+	fs::create_dir_all(installer_base)?;
+
+	let metadata_url = format!("{MAVEN_URL}net/neoforged/neoforge/maven-metadata.xml");
+	let metadata_path = upstream_base.join("maven-metadata.xml");
+	let metadata = with_retry(|| fetch_cached(client, &metadata_url, &metadata_path)).await?;
+	let metadata =
+		String::from_utf8(metadata).with_context(|| "maven-metadata.xml is not UTF-8")?;
+
+	lazy_static! {
+		static ref VERSION_PATTERN: Regex = Regex::new("<version>([^<]+)</version>").unwrap();
+	}
+	let versions: Vec<&str> = VERSION_PATTERN
+		.captures_iter(&metadata)
+		.map(|c| c.get(1).unwrap().as_str())
+		.collect();
+
+	futures::stream::iter(versions)
+		.map(Ok)
+		.try_for_each_concurrent(config.concurrency, |version| async move {
+			failures.check(
+				"neoforge",
+				version,
+				fetch_installer(client, installer_base, version).await,
+			)?;
+			Ok(())
+		})
+		.await
+}
+
+/// Downloads the installer of `version`, unless it was downloaded before. Released installers
+/// don't change, so they are not checked again.
+async fn fetch_installer(
+	client: &reqwest::Client,
+	installer_base: &Path,
+	version: &str,
+) -> Result<()> {
+	let installer = installer(version);
+	let path = installer_base.join(installer.file_name());
+	if path.try_exists()? {
+		return Ok(());
+	}
+	let content = with_retry(|| async {
+		Ok(client
+			.get(installer.to_url(MAVEN_URL))
+			.send()
+			.await?
+			.error_for_status()?
+			.bytes()
+			.await?)
+	})
+	.await?;
+	// written under a temporary name first, so that an interrupted download isn't mistaken for
+	// a complete one the next time
+	let partial_path = path.with_extension("part");
+	fs::write(&partial_path, content)?;
+	fs::rename(partial_path, path)?;
+
+	Ok(())
+}
+
+pub fn process(config: &Config, failures: &Failures) -> Result<()> {
+	forge::process_installers(config, failures, &NEOFORGE)
+}
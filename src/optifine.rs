@@ -0,0 +1,159 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+	fs,
+	io::{Read, Seek},
+	path::Path,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+use sha1::{Digest, Sha1};
+use zip::ZipArchive;
+
+use helixlauncher_meta as helix;
+use helixlauncher_meta::util::GradleSpecifier;
+
+use crate::{config::Config, forge, index};
+
+const ID: &str = "optifine.OptiFine";
+
+/// Where `Config`, which holds the version, is in the different releases.
+const CONFIG_CLASSES: [&str; 2] = ["net/optifine/Config.class", "Config.class"];
+
+/// Generates a component for an OptiFine jar the user downloaded, as OptiFine can't be
+/// redistributed. Both the installer and the jar extracted from it to install OptiFine as a mod
+/// contain the patched classes and the tweaker, so either is put on the classpath as is, from a
+/// `file:` URL. As that URL only works on this machine, the component is written to
+/// [Paths::local_root](crate::config::Paths::local_root) rather than the published output.
+pub fn process_jar(config: &Config, path: &Path) -> Result<helix::component::Component> {
+	let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+	let mut archive = ZipArchive::new(std::io::Cursor::new(&content))?;
+
+	let (config_class, release_time) = read_config_class(&mut archive)?;
+	lazy_static! {
+		// javac folds `VERSION = "OptiFine_" + MC_VERSION + "_" + OF_EDITION + "_" + OF_RELEASE`
+		// into a single constant
+		static ref VERSION_PATTERN: Regex =
+			Regex::new("OptiFine_([0-9.]+)_(HD_U_[A-Za-z0-9_]+)").unwrap();
+	}
+	let captures = VERSION_PATTERN
+		.captures(&config_class)
+		.with_context(|| "Could not find the OptiFine version")?;
+	let minecraft_version = std::str::from_utf8(&captures[1])?.to_owned();
+	let version = format!("{minecraft_version}_{}", std::str::from_utf8(&captures[2])?);
+
+	if archive
+		.index_for_name("optifine/OptiFineTweaker.class")
+		.is_none()
+	{
+		bail!("{} has no OptiFine tweaker", path.display());
+	}
+
+	let url = reqwest::Url::from_file_path(fs::canonicalize(path)?)
+		.map_err(|()| anyhow!("Cannot make a URL of {}", path.display()))?;
+	let name = GradleSpecifier {
+		group: "optifine".into(),
+		artifact: "OptiFine".into(),
+		version: version.clone(),
+		classifier: None,
+		extension: "jar".into(),
+	};
+	let mut downloads = vec![helix::component::Download {
+		name: name.clone(),
+		url: url.to_string(),
+		size: content.len().try_into()?,
+		hash: helix::component::Hash::SHA1(Sha1::digest(&content).into()),
+	}];
+
+	// releases for 1.13 and up bring their own build of LaunchWrapper, as the one from Mojang
+	// doesn't run on newer Java versions
+	let launchwrapper = match archive.index_for_name("launchwrapper-of.txt") {
+		Some(index) => {
+			let mut launchwrapper_version = String::new();
+			archive
+				.by_index(index)?
+				.read_to_string(&mut launchwrapper_version)?;
+			let launchwrapper_version = launchwrapper_version.trim();
+			let file_name = format!("launchwrapper-of-{launchwrapper_version}.jar");
+			let mut jar = vec![];
+			archive
+				.by_name(&file_name)
+				.with_context(|| format!("{file_name} is missing"))?
+				.read_to_end(&mut jar)?;
+			helix::component::Download {
+				name: GradleSpecifier {
+					group: "optifine".into(),
+					artifact: "launchwrapper-of".into(),
+					version: launchwrapper_version.into(),
+					classifier: None,
+					extension: "jar".into(),
+				},
+				url: format!("jar:{url}!/{file_name}"),
+				size: jar.len().try_into()?,
+				hash: helix::component::Hash::SHA1(Sha1::digest(&jar).into()),
+			}
+		}
+		None => forge::launchwrapper()?,
+	};
+
+	let classpath = vec![
+		helix::component::ConditionalClasspathEntry::All(name),
+		helix::component::ConditionalClasspathEntry::All(launchwrapper.name.clone()),
+	];
+	downloads.push(launchwrapper);
+
+	let component = helix::component::Component {
+		dependencies: forge::requires_minecraft(minecraft_version),
+		downloads,
+		classpath,
+		main_class: Some("net.minecraft.launchwrapper.Launch".into()),
+		tweakers: vec!["optifine.OptiFineTweaker".into()],
+		..helix::component::Component::new(ID, version, release_time)
+	};
+	component.validate()?;
+
+	let out_base = &config.paths.local_root.join(ID);
+	fs::create_dir_all(out_base)?;
+	index::write_component(out_base, &component)?;
+	index::write_index(out_base, index::load_entries(out_base)?)?;
+	Ok(component)
+}
+
+/// Reads the `Config` class, and takes its modification time as the release time, as OptiFine
+/// jars have no other record of it.
+fn read_config_class(
+	archive: &mut ZipArchive<impl Read + Seek>,
+) -> Result<(Vec<u8>, DateTime<Utc>)> {
+	let index = CONFIG_CLASSES
+		.iter()
+		.find_map(|name| archive.index_for_name(name))
+		.with_context(|| "Not an OptiFine jar")?;
+	let mut file = archive.by_index(index)?;
+	let modified = file
+		.last_modified()
+		.with_context(|| "Config has no modification time")?;
+	let release_time = NaiveDate::from_ymd_opt(
+		modified.year().into(),
+		modified.month().into(),
+		modified.day().into(),
+	)
+	.and_then(|date| {
+		date.and_hms_opt(
+			modified.hour().into(),
+			modified.minute().into(),
+			modified.second().into(),
+		)
+	})
+	.with_context(|| "Config has an invalid modification time")?
+	.and_utc();
+	let mut content = vec![];
+	file.read_to_end(&mut content)?;
+	Ok((content, release_time))
+}
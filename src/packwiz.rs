@@ -0,0 +1,135 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Pins of loader components for packwiz modpacks.
+
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+
+use helixlauncher_meta as helix;
+
+/// Loader components, and the keys packwiz uses for them under `[versions]`.
+const LOADERS: &[(&str, &str)] = &[
+	("net.fabricmc.fabric-loader", "fabric"),
+	("org.quiltmc.quilt-loader", "quilt"),
+	("net.minecraftforge.forge", "forge"),
+	("net.neoforged.neoforge", "neoforge"),
+	("com.mumfrey.liteloader", "liteloader"),
+];
+
+/// Returns the `[versions]` table of a packwiz `pack.toml` that pins the loader `component`.
+///
+/// The Minecraft version is taken from the requirements of the component. Loaders that work with
+/// any Minecraft version, such as Fabric's, don't pin one, so it has to be given as
+/// `minecraft_version` for them.
+pub fn pack_versions(
+	component: &helix::component::Component,
+	minecraft_version: Option<&str>,
+) -> Result<String> {
+	let (_, key) = LOADERS
+		.iter()
+		.find(|(id, _)| *id == component.id)
+		.with_context(|| format!("{} is not a loader packwiz knows", component.id))?;
+	let minecraft_version = component
+		.dependencies
+		.requires
+		.iter()
+		.find(|dependency| dependency.id == "net.minecraft")
+		.and_then(|dependency| dependency.version.as_deref())
+		.or(minecraft_version)
+		.with_context(|| {
+			format!(
+				"{} {} doesn't require a specific Minecraft version, and none was given",
+				component.id, component.version
+			)
+		})?;
+
+	let mut toml = String::from("[versions]\n");
+	writeln!(toml, "minecraft = {}", toml_string(minecraft_version))?;
+	writeln!(toml, "{key} = {}", toml_string(&component.version))?;
+	Ok(toml)
+}
+
+/// Quotes `s` as a TOML basic string.
+fn toml_string(s: &str) -> String {
+	let mut quoted = String::from('"');
+	for c in s.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			c if c.is_control() => write!(quoted, "\\u{:04X}", c as u32).unwrap(),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use helix::component::ComponentDependency;
+
+	/// A loader component with the contents of a Minecraft version, which don't matter here.
+	fn loader(
+		id: &str,
+		version: &str,
+		minecraft_version: Option<&str>,
+	) -> helix::component::Component {
+		let mut component = crate::mojang::tests::process_fixture("1.19.json");
+		component.id = id.into();
+		component.version = version.into();
+		component.dependencies.requires = vec![ComponentDependency {
+			id: "net.minecraft".into(),
+			version: minecraft_version.map(Into::into),
+		}];
+		component
+	}
+
+	#[test]
+	fn minecraft_version_is_taken_from_requires() {
+		let forge = loader("net.minecraftforge.forge", "41.1.0", Some("1.19"));
+		assert_eq!(
+			pack_versions(&forge, None).unwrap(),
+			"[versions]\nminecraft = \"1.19\"\nforge = \"41.1.0\"\n"
+		);
+		// the requirement wins over the given version
+		assert_eq!(
+			pack_versions(&forge, Some("1.20")).unwrap(),
+			pack_versions(&forge, None).unwrap()
+		);
+
+		let neoforge = loader("net.neoforged.neoforge", "20.2.86", Some("1.20.2"));
+		assert_eq!(
+			pack_versions(&neoforge, None).unwrap(),
+			"[versions]\nminecraft = \"1.20.2\"\nneoforge = \"20.2.86\"\n"
+		);
+	}
+
+	#[test]
+	fn loaders_for_any_minecraft_version_need_one_given() {
+		let fabric = loader("net.fabricmc.fabric-loader", "0.14.21", None);
+		assert!(pack_versions(&fabric, None).is_err());
+		assert_eq!(
+			pack_versions(&fabric, Some("1.19")).unwrap(),
+			"[versions]\nminecraft = \"1.19\"\nfabric = \"0.14.21\"\n"
+		);
+	}
+
+	#[test]
+	fn other_components_are_rejected() {
+		let minecraft = crate::mojang::tests::process_fixture("1.19.json");
+		assert!(pack_versions(&minecraft, Some("1.19")).is_err());
+	}
+
+	#[test]
+	fn toml_strings_are_escaped() {
+		assert_eq!(toml_string("1.19"), "\"1.19\"");
+		assert_eq!(toml_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000A\"");
+	}
+}
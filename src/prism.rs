@@ -0,0 +1,1337 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Conversion of components to and from the meta format of MultiMC and Prism Launcher, whose
+//! version files are "patches" of an instance.
+
+use std::{
+	borrow::Cow,
+	cmp::Reverse,
+	collections::{BTreeMap, BTreeSet, HashMap},
+	fs,
+	path::Path,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Utc};
+use data_encoding::HEXLOWER;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use helixlauncher_meta as helix;
+use helixlauncher_meta::{
+	component::{Arch, ConditionalClasspathEntry, MinecraftArgument, OsName, Platform, Trait},
+	util::GradleSpecifier,
+};
+
+use crate::{
+	artifact_download, artifact_info_path, config::Config, fetch_artifact_info, index,
+	report::Failures, write_if_changed, write_json,
+};
+
+/// The version of the Prism meta format written here.
+const FORMAT_VERSION: u32 = 1;
+
+/// Components that Prism knows under another uid. Dependencies are renamed too.
+const UIDS: &[(&str, &str)] = &[
+	("net.minecraftforge.forge", "net.minecraftforge"),
+	("net.neoforged.neoforge", "net.neoforged"),
+];
+
+fn uid(id: &str) -> &str {
+	UIDS.iter()
+		.find(|(helix_id, _)| *helix_id == id)
+		.map_or(id, |(_, uid)| uid)
+}
+
+fn helix_id(uid: &str) -> &str {
+	UIDS.iter()
+		.find(|(_, prism_uid)| *prism_uid == uid)
+		.map_or(uid, |(id, _)| id)
+}
+
+/// The OS names Prism uses in rules and `natives`. Those without an architecture are for
+/// anything but ARM.
+const OS_NAMES: &[(&str, OsName, Option<Arch>)] = &[
+	("linux", OsName::Linux, None),
+	("osx", OsName::Osx, None),
+	("windows", OsName::Windows, None),
+	("linux-arm64", OsName::Linux, Some(Arch::Arm64)),
+	("osx-arm64", OsName::Osx, Some(Arch::Arm64)),
+	("windows-arm64", OsName::Windows, Some(Arch::Arm64)),
+	("linux-arm32", OsName::Linux, Some(Arch::Arm32)),
+];
+
+/// Where Prism looks for libraries that have neither a download nor a repository.
+const LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
+
+/// A version file of Prism. Unknown keys are rejected when importing, as they are constructs
+/// that can't be converted.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Patch {
+	pub format_version: u32,
+	pub uid: String,
+	pub name: String,
+	pub version: String,
+	pub release_time: DateTime<Utc>,
+	#[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub requires: Vec<Require>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub conflicts: Vec<Require>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub main_class: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub minecraft_arguments: Option<String>,
+	#[serde(rename = "+traits", default, skip_serializing_if = "Vec::is_empty")]
+	pub traits: Vec<String>,
+	#[serde(rename = "+tweakers", default, skip_serializing_if = "Vec::is_empty")]
+	pub tweakers: Vec<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub asset_index: Option<AssetIndex>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub main_jar: Option<Library>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub libraries: Vec<Library>,
+	/// Files that are downloaded to the libraries directory, but not put on the classpath.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub maven_files: Vec<Library>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub compatible_java_majors: Vec<u32>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub compatible_java_name: Option<String>,
+	#[serde(rename = "+jvmArgs", default, skip_serializing_if = "Vec::is_empty")]
+	pub jvm_args: Vec<String>,
+	/// Where Prism sorts the patch in the list of an instance, which doesn't apply to components.
+	#[serde(rename = "order", default, skip_serializing)]
+	_order: Option<i32>,
+	/// Whether Prism removes the patch along with the one requiring it.
+	#[serde(rename = "volatile", default, skip_serializing)]
+	_volatile: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Require {
+	pub uid: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub equals: Option<String>,
+	/// The version Prism picks by default, which is not a requirement.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub suggests: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndex {
+	pub id: String,
+	pub sha1: String,
+	pub size: u32,
+	pub total_size: u32,
+	pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Library {
+	pub name: GradleSpecifier,
+	#[serde(default)]
+	pub downloads: LibraryDownloads,
+	/// The base URL of the Maven repository the library is on, for libraries without downloads.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+	/// OS -> classifier of the natives for it. The classifier may contain `${arch}`, which is
+	/// `32` or `64`.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub natives: BTreeMap<String, String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub extract: Option<Extract>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub rules: Vec<Rule>,
+	/// How Prism gets the library if not by download, such as `local` for files the user
+	/// provides.
+	#[serde(rename = "MMC-hint", default, skip_serializing_if = "Option::is_none")]
+	pub hint: Option<String>,
+	/// The URL of the library, for libraries that aren't in a Maven repository.
+	#[serde(
+		rename = "MMC-absoluteUrl",
+		default,
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub absolute_url: Option<String>,
+}
+
+impl Library {
+	/// A library that is downloaded on every platform.
+	fn plain(name: &GradleSpecifier, artifact: Artifact) -> Self {
+		Self {
+			name: name.clone(),
+			downloads: LibraryDownloads {
+				artifact: Some(artifact),
+				classifiers: BTreeMap::new(),
+			},
+			url: None,
+			natives: BTreeMap::new(),
+			extract: None,
+			rules: vec![],
+			hint: None,
+			absolute_url: None,
+		}
+	}
+
+	/// The URL of `file`, which is either this library or one of its natives, if Prism lists
+	/// no download for it.
+	fn url(&self, file: &GradleSpecifier) -> String {
+		match &self.absolute_url {
+			Some(url) => url.clone(),
+			None => file.to_url(self.url.as_deref().unwrap_or(LIBRARIES_URL)),
+		}
+	}
+
+	/// The natives of this library that its rules allow, with the OS and architecture they are
+	/// for.
+	fn native_files(&self) -> Result<Vec<(OsName, Option<Arch>, GradleSpecifier)>> {
+		let mut natives = vec![];
+		for (key, classifier) in &self.natives {
+			let &(_, os, arch) = OS_NAMES
+				.iter()
+				.find(|(name, _, _)| name == key)
+				.with_context(|| format!("Unknown OS {key} in the natives of {}", self.name))?;
+			if !allows(&self.rules, key) {
+				continue;
+			}
+			// natives without an architecture are only limited to x86 if there are ARM ones
+			let arch = arch.or_else(|| {
+				self.natives
+					.keys()
+					.any(|other| other.starts_with(&format!("{key}-")))
+					.then_some(Arch::X86_64)
+			});
+			let name = |classifier: String| GradleSpecifier {
+				classifier: Some(classifier),
+				..self.name.clone()
+			};
+			if classifier.contains("${arch}") {
+				natives.push((
+					os,
+					Some(Arch::X86),
+					name(classifier.replace("${arch}", "32")),
+				));
+				natives.push((
+					os,
+					Some(Arch::X86_64),
+					name(classifier.replace("${arch}", "64")),
+				));
+			} else {
+				natives.push((os, arch, name(classifier.clone())));
+			}
+		}
+		Ok(natives)
+	}
+
+	/// Every file of this library, with the download Prism lists for it, if any: the library
+	/// itself, unless it only consists of natives, and the natives.
+	fn files(&self) -> Result<Vec<(GradleSpecifier, Option<&Artifact>)>> {
+		let mut files = vec![];
+		if self.natives.is_empty() || self.downloads.artifact.is_some() {
+			files.push((self.name.clone(), self.downloads.artifact.as_ref()));
+		}
+		for (_, _, name) in self.native_files()? {
+			let artifact = self
+				.downloads
+				.classifiers
+				.get(name.classifier.as_ref().unwrap());
+			files.push((name, artifact));
+		}
+		Ok(files)
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LibraryDownloads {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub artifact: Option<Artifact>,
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub classifiers: BTreeMap<String, Artifact>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Artifact {
+	pub sha1: String,
+	pub size: u32,
+	pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Extract {
+	pub exclude: Vec<String>,
+}
+
+/// A rule deciding whether a library is used, as in Mojang's version files: the last rule that
+/// matches decides, and libraries with rules of which none match are not used.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+	pub action: RuleAction,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub os: Option<RuleOs>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+	Allow,
+	Disallow,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RuleOs {
+	pub name: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub version: Option<String>,
+}
+
+/// Whether `rules` allow the OS Prism calls `os_name`.
+fn allows(rules: &[Rule], os_name: &str) -> bool {
+	if rules.is_empty() {
+		return true;
+	}
+	let mut allowed = false;
+	for rule in rules {
+		if rule.os.as_ref().is_none_or(|os| os.name == os_name) {
+			allowed = rule.action == RuleAction::Allow;
+		}
+	}
+	allowed
+}
+
+/// The per-component index, listing the versions of a uid.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PackageIndex {
+	format_version: u32,
+	name: String,
+	uid: String,
+	versions: Vec<PackageVersion>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PackageVersion {
+	version: String,
+	release_time: DateTime<Utc>,
+	#[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+	channel: Option<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	requires: Vec<Require>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	conflicts: Vec<Require>,
+	sha256: String,
+}
+
+/// The top-level index, listing every uid.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Packages {
+	format_version: u32,
+	packages: Vec<Package>,
+}
+
+#[derive(Serialize, Debug)]
+struct Package {
+	name: String,
+	uid: String,
+	sha256: String,
+}
+
+/// Converts `component` into a Prism patch.
+///
+/// Prism builds the JVM arguments and handles custom resolutions and demo mode itself, so
+/// `jvm_arguments` and conditional game arguments are left out, as are `provides`, `optional`
+/// and the logging configuration, which Prism has no equivalent for. As Prism splits the game
+/// arguments on whitespace, empty ones are left out with their option. Installers, jar mods and
+/// Java runtimes can't be expressed and are rejected.
+pub fn export(component: &helix::component::Component) -> Result<Patch> {
+	ensure!(
+		component.installer.is_none(),
+		"Installers are not supported by Prism patches"
+	);
+	ensure!(
+		component.jarmods.is_empty(),
+		"Jar mods are not supported by Prism patches"
+	);
+	ensure!(
+		component.java_runtimes.is_empty(),
+		"Java runtimes are not supported by Prism patches"
+	);
+
+	let downloads: HashMap<_, _> = component
+		.downloads
+		.iter()
+		.map(|download| (&download.name, download))
+		.collect();
+	let artifact = |name: &GradleSpecifier| -> Result<Artifact> {
+		let download = downloads
+			.get(name)
+			.with_context(|| format!("{name} has no download"))?;
+		let helix::component::Hash::SHA1(sha1) = &download.hash else {
+			bail!("{name} has a {}, but Prism needs a SHA-1", download.hash);
+		};
+		Ok(Artifact {
+			sha1: HEXLOWER.encode(sha1),
+			size: download.size,
+			url: download.url.clone(),
+		})
+	};
+
+	let mut libraries = vec![];
+	for entry in &component.classpath {
+		let (name, rules) = match entry {
+			ConditionalClasspathEntry::All(name) => (name, vec![]),
+			ConditionalClasspathEntry::PlatformSpecific { name, platform } => {
+				let rules = rules(platform)?;
+				// without rules, the library would be used everywhere, see the natives below
+				if rules.is_empty() {
+					continue;
+				}
+				(name, rules)
+			}
+		};
+		libraries.push(Library {
+			rules,
+			..Library::plain(name, artifact(name)?)
+		});
+	}
+	for native in &component.natives {
+		let classifier = native
+			.name
+			.classifier
+			.clone()
+			.with_context(|| format!("Native {} has no classifier", native.name))?;
+		let os_names = os_names(&native.platform)?;
+		// Prism can't tell x86 and x86_64 hosts apart, and has no name for RISC-V, so those
+		// natives are left out
+		if os_names.is_empty() {
+			continue;
+		}
+		libraries.push(Library {
+			name: GradleSpecifier {
+				classifier: None,
+				..native.name.clone()
+			},
+			downloads: LibraryDownloads {
+				artifact: None,
+				classifiers: BTreeMap::from([(classifier.clone(), artifact(&native.name)?)]),
+			},
+			url: None,
+			natives: os_names
+				.into_iter()
+				.map(|os| (os, classifier.clone()))
+				.collect(),
+			extract: (!native.exclusions.is_empty()).then(|| Extract {
+				exclude: native.exclusions.clone(),
+			}),
+			rules: vec![],
+			hint: None,
+			absolute_url: None,
+		});
+	}
+
+	let main_jar = component
+		.game_jar
+		.as_ref()
+		.map(|name| anyhow::Ok(Library::plain(name, artifact(name)?)))
+		.transpose()?;
+
+	// downloads that are on neither the classpath nor the natives, such as files a tweaker loads
+	// on its own
+	let mut maven_files = vec![];
+	for download in &component.downloads {
+		let referenced = component.game_jar.as_ref() == Some(&download.name)
+			|| component.classpath.iter().any(|entry| match entry {
+				ConditionalClasspathEntry::All(name)
+				| ConditionalClasspathEntry::PlatformSpecific { name, .. } => *name == download.name,
+			}) || component
+			.natives
+			.iter()
+			.any(|native| native.name == download.name);
+		if !referenced {
+			maven_files.push(Library::plain(&download.name, artifact(&download.name)?));
+		}
+	}
+
+	let mut arguments = vec![];
+	for argument in &component.game_arguments {
+		if let MinecraftArgument::Always(value) = argument {
+			// minecraftArguments is split on whitespace, so an empty value, such as the client id,
+			// can't be expressed; the option it belongs to is left out with it
+			if value.is_empty() {
+				if arguments
+					.last()
+					.is_some_and(|last: &Cow<'_, str>| last.starts_with("--"))
+				{
+					arguments.pop();
+				}
+				continue;
+			}
+			arguments.push(remap_vars(value)?);
+		}
+	}
+
+	let mut traits = vec![];
+	for kind in component.traits.iter().chain(
+		component
+			.platform_traits
+			.iter()
+			.filter(|platform_trait| platform_trait.platform.os.contains(&OsName::Osx))
+			.map(|platform_trait| &platform_trait.kind),
+	) {
+		let name = match kind {
+			Trait::MacStartOnFirstThread => "FirstThreadOnMacOS",
+			Trait::SupportsQuickPlayServer => "feature:is_quick_play_multiplayer",
+			Trait::SupportsQuickPlayWorld => "feature:is_quick_play_singleplayer",
+			// Prism always supports these, or decides on its own
+			Trait::SupportsCustomResolution
+			| Trait::SupportsQuickPlayServerLegacy
+			| Trait::LegacyAssets => continue,
+		};
+		if !traits.iter().any(|existing| existing == name) {
+			traits.push(name.to_owned());
+		}
+	}
+
+	let requires = |dependencies: &[helix::component::ComponentDependency]| {
+		dependencies
+			.iter()
+			.map(|dependency| Require {
+				uid: uid(&dependency.id).to_owned(),
+				equals: dependency.version.clone(),
+				suggests: None,
+			})
+			.collect()
+	};
+
+	Ok(Patch {
+		format_version: FORMAT_VERSION,
+		uid: uid(&component.id).to_owned(),
+		name: uid(&component.id).to_owned(),
+		version: component.version.clone(),
+		release_time: component.release_time,
+		channel: component.channel.clone(),
+		requires: requires(&component.dependencies.requires),
+		conflicts: requires(&component.dependencies.conflicts),
+		main_class: component.main_class.clone(),
+		minecraft_arguments: (!arguments.is_empty()).then(|| arguments.join(" ")),
+		traits,
+		tweakers: component.tweakers.clone(),
+		asset_index: component.assets.as_ref().map(|assets| AssetIndex {
+			id: assets.id.clone(),
+			sha1: assets.sha1.clone(),
+			size: assets.size,
+			total_size: assets.total_size,
+			url: assets.url.clone(),
+		}),
+		main_jar,
+		libraries,
+		maven_files,
+		compatible_java_majors: component.min_java_version.into_iter().collect(),
+		compatible_java_name: component.java_component.clone(),
+		jvm_args: vec![],
+		_order: None,
+		_volatile: None,
+	})
+}
+
+/// The names Prism uses in rules and `natives` for the OSes of `platform`, or none for
+/// architectures Prism has no names for, which [export] leaves out.
+fn os_names(platform: &Platform) -> Result<Vec<String>> {
+	ensure!(
+		!platform.os.is_empty(),
+		"Platforms without an OS are not supported"
+	);
+	let suffix = match platform.arch {
+		None | Some(Arch::X86_64) => "",
+		Some(Arch::Arm64) => "-arm64",
+		Some(Arch::Arm32) => "-arm32",
+		Some(Arch::X86 | Arch::Riscv64) => return Ok(vec![]),
+	};
+	Ok(platform
+		.os
+		.iter()
+		.map(|os| {
+			let os = match os {
+				OsName::Linux => "linux",
+				OsName::Osx => "osx",
+				OsName::Windows => "windows",
+			};
+			format!("{os}{suffix}")
+		})
+		.collect())
+}
+
+/// The rules allowing `platform`. The OS names without an architecture exclude ARM, so a
+/// platform for any architecture is allowed on the ARM names of its OSes as well, unless it has
+/// an OS version, which [import] only supports in a single rule.
+fn rules(platform: &Platform) -> Result<Vec<Rule>> {
+	let mut names = os_names(platform)?;
+	if platform.arch.is_none() && platform.os_version.is_none() {
+		names.extend(
+			OS_NAMES
+				.iter()
+				.filter(|(_, os, arch)| arch.is_some() && platform.os.contains(os))
+				.map(|(name, _, _)| (*name).to_owned()),
+		);
+	}
+	Ok(names
+		.into_iter()
+		.map(|name| Rule {
+			action: RuleAction::Allow,
+			os: Some(RuleOs {
+				name,
+				version: platform.os_version.clone(),
+			}),
+		})
+		.collect())
+}
+
+/// Placeholders of Helix, and the ones Prism takes from Mojang for them.
+const VARS: &[(&str, &str)] = &[
+	("user.token", "auth_access_token"),
+	("user.name", "auth_player_name"),
+	("user.uuid", "auth_uuid"),
+	("user.type", "user_type"),
+	("instance.minecraft_version", "version_name"),
+	("instance.game_dir", "game_directory"),
+	("instance.assets_dir", "assets_root"),
+	("instance.assets_index_name", "assets_index_name"),
+	("instance.virtual_assets_dir", "game_assets"),
+	("instance.natives_dir", "natives_directory"),
+	("launcher.name", "launcher_name"),
+	("launcher.version", "launcher_version"),
+	("launch.classpath", "classpath"),
+];
+
+/// Maps the placeholders of Helix back to the ones Prism takes from Mojang.
+fn remap_vars(s: &str) -> Result<Cow<'_, str>> {
+	replace_vars(s, |name| {
+		VARS.iter()
+			.find(|(helix_name, _)| *helix_name == name)
+			.map(|(_, prism_name)| format!("${{{prism_name}}}"))
+	})
+}
+
+/// Maps the placeholders of Prism to the ones of Helix, like [crate::mojang] does for Mojang's.
+fn import_vars<'a>(s: &'a str, channel: Option<&str>) -> Result<Cow<'a, str>> {
+	replace_vars(s, |name| {
+		Some(match name {
+			"auth_session" => "${user.token}".into(),
+			"clientid" | "auth_xuid" => "".into(),
+			"user_properties" => "{}".into(),
+			"version_type" => channel?.into(),
+			_ => VARS
+				.iter()
+				.find(|(_, prism_name)| *prism_name == name)
+				.map(|(helix_name, _)| format!("${{{helix_name}}}"))?,
+		})
+	})
+}
+
+/// Replaces every `${name}` in `s` with `replacement(name)`, failing if that is `None` for any
+/// of them.
+fn replace_vars(s: &str, replacement: impl Fn(&str) -> Option<String>) -> Result<Cow<'_, str>> {
+	lazy_static! {
+		static ref VAR_PATTERN: Regex = Regex::new("\\$\\{([a-zA-Z0-9_.]+)\\}").unwrap();
+	}
+	if let Some(unknown) = VAR_PATTERN
+		.captures_iter(s)
+		.find(|c| replacement(&c[1]).is_none())
+	{
+		bail!("{} is not supported", &unknown[0]);
+	}
+	Ok(VAR_PATTERN.replace_all(s, |c: &Captures<'_>| replacement(&c[1]).unwrap()))
+}
+
+/// Writes the components `ids` in Prism's layout to `out_base`: `<uid>/<version>.json`, an
+/// `index.json` per uid, and a top-level `index.json` listing every uid in `out_base`.
+pub fn export_all(
+	config: &Config,
+	failures: &Failures,
+	out_base: &Path,
+	ids: &[String],
+) -> Result<()> {
+	fs::create_dir_all(out_base)?;
+
+	for id in ids {
+		let uid = uid(id);
+		let package_base = &out_base.join(uid);
+		fs::create_dir_all(package_base)?;
+
+		let mut components = index::load_components(&config.paths.out_root.join(id))
+			.with_context(|| format!("Failed to load the components of {id}"))?;
+		// the same order as index::write_index
+		components
+			.sort_by_key(|component| (Reverse(component.release_time), component.version.clone()));
+
+		let mut versions = vec![];
+		for component in components {
+			let Some(patch) = failures.check(
+				"prism",
+				format!("{id} {}", component.version),
+				export(&component),
+			)?
+			else {
+				continue;
+			};
+			let content = serde_json::to_vec_pretty(&patch)?;
+			write_if_changed(
+				&package_base.join(format!("{}.json", patch.version)),
+				&content,
+			)?;
+			versions.push(PackageVersion {
+				version: patch.version,
+				release_time: patch.release_time,
+				channel: patch.channel,
+				requires: patch.requires,
+				conflicts: patch.conflicts,
+				sha256: HEXLOWER.encode(&Sha256::digest(&content)),
+			});
+		}
+
+		let content = serde_json::to_vec_pretty(&PackageIndex {
+			format_version: FORMAT_VERSION,
+			name: uid.to_owned(),
+			uid: uid.to_owned(),
+			versions,
+		})?;
+		write_if_changed(&package_base.join("index.json"), &content)?;
+	}
+
+	// packages exported before are kept, so that components can be exported a few at a time
+	let mut dirs = index::component_dirs(out_base)?;
+	dirs.sort();
+	let mut packages = vec![];
+	for dir in dirs {
+		let Ok(content) = fs::read(dir.join("index.json")) else {
+			continue;
+		};
+		let uid = dir.file_name().unwrap().to_str().unwrap();
+		packages.push(Package {
+			name: uid.to_owned(),
+			uid: uid.to_owned(),
+			sha256: HEXLOWER.encode(&Sha256::digest(&content)),
+		});
+	}
+	write_json(
+		&out_base.join("index.json"),
+		&Packages {
+			format_version: FORMAT_VERSION,
+			packages,
+		},
+	)
+}
+
+/// The platforms `rules` allow a library on, or `None` if they allow it everywhere.
+fn platforms(library: &Library) -> Result<Option<Vec<Platform>>> {
+	let rules = &library.rules;
+	for os in rules.iter().filter_map(|rule| rule.os.as_ref()) {
+		ensure!(
+			OS_NAMES.iter().any(|(name, _, _)| *name == os.name),
+			"Unknown OS {} in the rules of {}",
+			os.name,
+			library.name
+		);
+	}
+	if let Some(os) = rules
+		.iter()
+		.filter_map(|rule| rule.os.as_ref())
+		.find(|os| os.version.is_some())
+	{
+		// the OS version can't be checked here, so only a single rule using it can be converted
+		ensure!(
+			rules.len() == 1 && rules[0].action == RuleAction::Allow,
+			"OS version rules combined with other rules are not supported ({})",
+			library.name
+		);
+		let &(_, os_name, arch) = OS_NAMES
+			.iter()
+			.find(|(name, _, _)| *name == os.name)
+			.unwrap();
+		return Ok(Some(vec![Platform {
+			os: vec![os_name],
+			arch,
+			os_version: os.version.clone(),
+		}]));
+	}
+
+	let allowed: Vec<_> = OS_NAMES
+		.iter()
+		.filter(|(name, _, _)| allows(rules, name))
+		.collect();
+	if allowed.len() == OS_NAMES.len() {
+		return Ok(None);
+	}
+	let os_of = |arch: Option<Arch>| -> Vec<OsName> {
+		allowed
+			.iter()
+			.filter(|(_, _, allowed_arch)| *allowed_arch == arch)
+			.map(|(_, os, _)| *os)
+			.collect()
+	};
+	let any_arch = os_of(None);
+	// if the rules allow an OS on every architecture or on none, the architecture doesn't matter
+	let ignores_arch = OS_NAMES.iter().all(|(name, os, arch)| {
+		arch.is_none()
+			|| allowed.iter().any(|(allowed, _, _)| allowed == name) == any_arch.contains(os)
+	});
+	if ignores_arch {
+		return Ok(Some(if any_arch.is_empty() {
+			vec![]
+		} else {
+			vec![Platform {
+				os: any_arch,
+				arch: None,
+				os_version: None,
+			}]
+		}));
+	}
+	Ok(Some(
+		[
+			(None, Arch::X86_64),
+			(Some(Arch::Arm64), Arch::Arm64),
+			(Some(Arch::Arm32), Arch::Arm32),
+		]
+		.into_iter()
+		.map(|(key_arch, arch)| Platform {
+			os: os_of(key_arch),
+			arch: Some(arch),
+			os_version: None,
+		})
+		.filter(|platform| !platform.os.is_empty())
+		.collect(),
+	))
+}
+
+/// Every library of `patch`, wherever it goes.
+fn all_libraries(patch: &Patch) -> impl Iterator<Item = &Library> {
+	patch
+		.main_jar
+		.iter()
+		.chain(&patch.libraries)
+		.chain(&patch.maven_files)
+}
+
+/// Looks up the hashes and sizes of the files of `patch` that Prism lists no download for.
+async fn fetch_missing(
+	client: &reqwest::Client,
+	upstream_base: &Path,
+	patch: &Patch,
+) -> Result<()> {
+	for library in all_libraries(patch) {
+		for (name, artifact) in library.files()? {
+			if artifact.is_none() {
+				fetch_artifact_info(
+					client,
+					&library.url(&name),
+					&artifact_info_path(upstream_base, &name),
+				)
+				.await?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Converts a Prism patch into a component. Files without a download have to be looked up with
+/// [fetch_missing] first.
+pub fn import(upstream_base: &Path, patch: Patch) -> Result<helix::component::Component> {
+	let mut downloads = vec![];
+	let mut download = |library: &Library, name: &GradleSpecifier, artifact: Option<&Artifact>| {
+		if let Some(hint) = &library.hint {
+			bail!(
+				"{} has MMC-hint {hint}, which is not supported",
+				library.name
+			);
+		}
+		downloads.push(match artifact {
+			Some(artifact) => helix::component::Download {
+				name: name.clone(),
+				url: artifact.url.clone(),
+				size: artifact.size,
+				hash: helix::component::Hash::parse_sha1(&artifact.sha1)?,
+			},
+			None => artifact_download(upstream_base, name, library.url(name))?.0,
+		});
+		Ok(())
+	};
+
+	let mut classpath = vec![];
+	let mut natives = vec![];
+	for library in &patch.libraries {
+		if library.natives.is_empty() || library.downloads.artifact.is_some() {
+			download(library, &library.name, library.downloads.artifact.as_ref())?;
+			match platforms(library)? {
+				None => classpath.push(ConditionalClasspathEntry::All(library.name.clone())),
+				Some(platforms) => classpath.extend(platforms.into_iter().map(|platform| {
+					ConditionalClasspathEntry::PlatformSpecific {
+						name: library.name.clone(),
+						platform,
+					}
+				})),
+			}
+		}
+		for (os, arch, name) in library.native_files()? {
+			let artifact = library
+				.downloads
+				.classifiers
+				.get(name.classifier.as_ref().unwrap());
+			download(library, &name, artifact)?;
+			natives.push(helix::component::Native {
+				name,
+				platform: Platform {
+					os: vec![os],
+					arch,
+					os_version: None,
+				},
+				exclusions: library
+					.extract
+					.as_ref()
+					.map_or_else(Vec::new, |extract| extract.exclude.clone()),
+			});
+		}
+	}
+	for library in &patch.maven_files {
+		download(library, &library.name, library.downloads.artifact.as_ref())?;
+	}
+	if let Some(main_jar) = &patch.main_jar {
+		download(
+			main_jar,
+			&main_jar.name,
+			main_jar.downloads.artifact.as_ref(),
+		)?;
+	}
+
+	let mut traits = BTreeSet::new();
+	let mut platform_traits = vec![];
+	for name in &patch.traits {
+		match name.as_str() {
+			"FirstThreadOnMacOS" => platform_traits.push(helix::component::PlatformTrait {
+				kind: Trait::MacStartOnFirstThread,
+				platform: Platform {
+					os: vec![OsName::Osx],
+					arch: None,
+					os_version: None,
+				},
+			}),
+			"feature:is_quick_play_multiplayer" => {
+				traits.insert(Trait::SupportsQuickPlayServer);
+			}
+			"feature:is_quick_play_singleplayer" => {
+				traits.insert(Trait::SupportsQuickPlayWorld);
+			}
+			// these only change what Prism shows
+			"XR:Initial" | "texturepacks" | "no-texturepacks" => {}
+			_ => bail!("Trait {name} is not supported"),
+		}
+	}
+	if patch
+		.asset_index
+		.as_ref()
+		.is_some_and(|assets| assets.id == "pre-1.6" || assets.id == "legacy")
+	{
+		traits.insert(Trait::LegacyAssets);
+	}
+
+	let channel = patch.channel.as_deref();
+	let game_arguments = patch
+		.minecraft_arguments
+		.iter()
+		.flat_map(|arguments| arguments.split_whitespace())
+		.map(|argument| {
+			Ok(MinecraftArgument::Always(
+				import_vars(argument, channel)?.into(),
+			))
+		})
+		.collect::<Result<_>>()?;
+	let jvm_arguments = patch
+		.jvm_args
+		.iter()
+		.map(|argument| {
+			Ok(MinecraftArgument::Always(
+				import_vars(argument, channel)?.into(),
+			))
+		})
+		.collect::<Result<_>>()?;
+
+	let dependencies = |requires: Vec<Require>| {
+		requires
+			.into_iter()
+			.map(|require| helix::component::ComponentDependency {
+				id: helix_id(&require.uid).to_owned(),
+				version: require.equals,
+			})
+			.collect()
+	};
+
+	let component = helix::component::Component {
+		channel: patch.channel.clone(),
+		dependencies: helix::component::Dependencies {
+			requires: dependencies(patch.requires),
+			conflicts: dependencies(patch.conflicts),
+			..Default::default()
+		},
+		traits,
+		platform_traits,
+		assets: patch.asset_index.map(|assets| helix::component::Assets {
+			id: assets.id,
+			url: assets.url,
+			sha1: assets.sha1,
+			size: assets.size,
+			total_size: assets.total_size,
+		}),
+		downloads,
+		game_jar: patch.main_jar.map(|main_jar| main_jar.name),
+		main_class: patch.main_class,
+		java_component: patch.compatible_java_name,
+		min_java_version: patch.compatible_java_majors.into_iter().min(),
+		game_arguments,
+		jvm_arguments,
+		tweakers: patch.tweakers,
+		classpath,
+		natives,
+		..helix::component::Component::new(helix_id(&patch.uid), patch.version, patch.release_time)
+	};
+	component.validate()?;
+	Ok(component)
+}
+
+/// Imports every version of the components `uids` from the Prism meta in `prism_base`, laid out
+/// like [export_all] writes it, into the output directory.
+pub async fn import_all(
+	client: &reqwest::Client,
+	config: &Config,
+	failures: &Failures,
+	prism_base: &Path,
+	uids: &[String],
+) -> Result<()> {
+	let upstream_base = &config.paths.upstream_root.join("prism");
+	fs::create_dir_all(upstream_base.join("artifacts"))?;
+
+	for uid in uids {
+		let out_base = &config.paths.out_root.join(helix_id(uid));
+		fs::create_dir_all(out_base)?;
+
+		let mut paths = vec![];
+		for file in fs::read_dir(prism_base.join(uid))
+			.with_context(|| format!("Failed to list the versions of {uid}"))?
+		{
+			let path = file?.path();
+			if path
+				.extension()
+				.is_some_and(|extension| extension == "json")
+				&& path.file_name().unwrap() != "index.json"
+			{
+				paths.push(path);
+			}
+		}
+		paths.sort();
+
+		for path in paths {
+			let result = async {
+				let patch: Patch = serde_json::from_slice(&fs::read(&path)?)
+					.with_context(|| format!("Failed to parse {}", path.display()))?;
+				fetch_missing(client, upstream_base, &patch).await?;
+				index::write_component(out_base, &import(upstream_base, patch)?)
+			}
+			.await;
+			failures.check("prism", path.display(), result)?;
+		}
+
+		index::write_index(out_base, index::load_entries(out_base)?)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::DateTime;
+	use helixlauncher_meta::component::{Component, Download, Hash};
+
+	use super::*;
+
+	#[test]
+	fn export_skips_classpath_entries_without_prism_names() {
+		let mut component = Component::new("net.minecraft", "1.19", DateTime::UNIX_EPOCH);
+		for (name, arch) in [
+			("org.lwjgl:lwjgl:3.3.1:natives-windows", Arch::X86_64),
+			("org.lwjgl:lwjgl:3.3.1:natives-windows-x86", Arch::X86),
+			("org.lwjgl:lwjgl:3.3.1:natives-linux-riscv64", Arch::Riscv64),
+		] {
+			let name: GradleSpecifier = name.parse().unwrap();
+			component.downloads.push(Download {
+				url: name.to_url(LIBRARIES_URL),
+				name: name.clone(),
+				size: 1,
+				hash: Hash::SHA1([0; 20]),
+			});
+			component
+				.classpath
+				.push(ConditionalClasspathEntry::PlatformSpecific {
+					name,
+					platform: Platform {
+						os: vec![OsName::Windows],
+						arch: Some(arch),
+						os_version: None,
+					},
+				});
+		}
+
+		let patch = export(&component).unwrap();
+		let names: Vec<_> = patch
+			.libraries
+			.iter()
+			.map(|library| library.name.to_string())
+			.collect();
+		assert_eq!(names, ["org.lwjgl:lwjgl:3.3.1:natives-windows"]);
+		assert!(!patch.libraries[0].rules.is_empty());
+	}
+
+	/// Exports `component` and imports it again. Every file has a download, so nothing is read
+	/// from the upstream directory.
+	fn round_trip(component: &Component) -> Component {
+		import(Path::new("upstream/prism"), export(component).unwrap()).unwrap()
+	}
+
+	fn always_arguments(arguments: &[MinecraftArgument]) -> Vec<&str> {
+		arguments
+			.iter()
+			.filter_map(|argument| match argument {
+				MinecraftArgument::Always(value) => Some(value.as_str()),
+				_ => None,
+			})
+			.collect()
+	}
+
+	fn library(library: serde_json::Value) -> Library {
+		serde_json::from_value(library).unwrap()
+	}
+
+	#[test]
+	fn vanilla_round_trips() {
+		let component = crate::mojang::tests::process_fixture("1.19.json");
+		let imported = round_trip(&component);
+
+		// Prism can't tell x86 and x86_64 hosts apart, so the x86 natives jars are left out
+		let classpath: Vec<_> = component
+			.classpath
+			.iter()
+			.filter(|entry| {
+				!matches!(
+					entry,
+					ConditionalClasspathEntry::PlatformSpecific {
+						platform: Platform {
+							arch: Some(Arch::X86),
+							..
+						},
+						..
+					}
+				)
+			})
+			.collect();
+		assert_eq!(imported.classpath.iter().collect::<Vec<_>>(), classpath);
+		assert_eq!(imported.natives, component.natives);
+		// Prism always supports custom resolutions, so it has no trait for them
+		assert_eq!(
+			component.traits,
+			BTreeSet::from([Trait::SupportsCustomResolution])
+		);
+		assert!(imported.traits.is_empty());
+		assert_eq!(imported.platform_traits, component.platform_traits);
+		// conditional arguments are left to Prism, and the empty client id and xuid are dropped
+		assert_eq!(
+			always_arguments(&imported.game_arguments),
+			[
+				"--username",
+				"${user.name}",
+				"--version",
+				"${instance.minecraft_version}",
+				"--gameDir",
+				"${instance.game_dir}",
+				"--assetsDir",
+				"${instance.assets_dir}",
+				"--assetIndex",
+				"${instance.assets_index_name}",
+				"--uuid",
+				"${user.uuid}",
+				"--accessToken",
+				"${user.token}",
+				"--userType",
+				"${user.type}",
+				"--versionType",
+				"release",
+			]
+		);
+		assert_eq!(imported.main_class, component.main_class);
+		assert_eq!(imported.game_jar, component.game_jar);
+		assert_eq!(imported.min_java_version, component.min_java_version);
+		assert_eq!(imported.java_component, component.java_component);
+	}
+
+	#[test]
+	fn natives_round_trip() {
+		let component = crate::mojang::tests::process_fixture("version-gated-natives.json");
+		let imported = round_trip(&component);
+		let natives: Vec<_> = imported
+			.natives
+			.iter()
+			.map(|native| {
+				(
+					native.name.classifier.as_deref().unwrap(),
+					native.platform.clone(),
+				)
+			})
+			.collect();
+		let os = |os| Platform {
+			os: vec![os],
+			arch: None,
+			os_version: None,
+		};
+		// the x86 native is left out, and Prism's natives have no OS version
+		assert_eq!(
+			natives,
+			[
+				("natives-linux", os(OsName::Linux)),
+				("natives-osx", os(OsName::Osx)),
+				("natives-windows", os(OsName::Windows)),
+				("natives-windows-64", os(OsName::Windows)),
+			]
+		);
+		assert_eq!(
+			imported.natives[0].exclusions,
+			component.natives[0].exclusions
+		);
+	}
+
+	#[test]
+	fn arch_placeholders_in_natives_are_expanded() {
+		let library = library(serde_json::json!({
+			"name": "tv.twitch:twitch-external-platform:4.5",
+			"natives": { "windows": "natives-windows-${arch}" },
+		}));
+		let natives: Vec<_> = library
+			.native_files()
+			.unwrap()
+			.into_iter()
+			.map(|(os, arch, name)| (os, arch, name.to_string()))
+			.collect();
+		assert_eq!(
+			natives,
+			[
+				(
+					OsName::Windows,
+					Some(Arch::X86),
+					"tv.twitch:twitch-external-platform:4.5:natives-windows-32".into()
+				),
+				(
+					OsName::Windows,
+					Some(Arch::X86_64),
+					"tv.twitch:twitch-external-platform:4.5:natives-windows-64".into()
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn rules_are_converted_to_platforms() {
+		let platforms = |rules| {
+			platforms(&library(serde_json::json!({
+				"name": "org.example:library:1.0",
+				"rules": rules,
+			})))
+		};
+		let platform = |os: &[OsName], arch| Platform {
+			os: os.to_vec(),
+			arch,
+			os_version: None,
+		};
+
+		assert_eq!(platforms(serde_json::json!([])).unwrap(), None);
+		// the names without an architecture don't include ARM
+		assert_eq!(
+			platforms(serde_json::json!([{ "action": "allow", "os": { "name": "osx" } }])).unwrap(),
+			Some(vec![platform(&[OsName::Osx], Some(Arch::X86_64))])
+		);
+		assert_eq!(
+			platforms(serde_json::json!([
+				{ "action": "allow", "os": { "name": "osx" } },
+				{ "action": "allow", "os": { "name": "osx-arm64" } },
+			]))
+			.unwrap(),
+			Some(vec![platform(&[OsName::Osx], None)])
+		);
+		assert_eq!(
+			platforms(serde_json::json!([
+				{ "action": "allow" },
+				{ "action": "disallow", "os": { "name": "osx" } },
+			]))
+			.unwrap(),
+			Some(vec![
+				platform(&[OsName::Linux, OsName::Windows], Some(Arch::X86_64)),
+				platform(
+					&[OsName::Linux, OsName::Osx, OsName::Windows],
+					Some(Arch::Arm64)
+				),
+				platform(&[OsName::Linux], Some(Arch::Arm32)),
+			])
+		);
+		assert_eq!(
+			platforms(serde_json::json!([
+				{ "action": "allow", "os": { "name": "windows", "version": "^10\\." } },
+			]))
+			.unwrap(),
+			Some(vec![Platform {
+				os: vec![OsName::Windows],
+				arch: None,
+				os_version: Some("^10\\.".into()),
+			}])
+		);
+		assert!(platforms(serde_json::json!([
+			{ "action": "allow" },
+			{ "action": "disallow", "os": { "name": "windows", "version": "^10\\." } },
+		]))
+		.is_err());
+		assert!(
+			platforms(serde_json::json!([{ "action": "allow", "os": { "name": "beos" } }]))
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn prism_placeholders_are_imported() {
+		assert_eq!(
+			import_vars("${auth_player_name}", None).unwrap(),
+			"${user.name}"
+		);
+		assert_eq!(
+			import_vars("${auth_session}", None).unwrap(),
+			"${user.token}"
+		);
+		assert_eq!(import_vars("${clientid}", None).unwrap(), "");
+		assert_eq!(import_vars("${user_properties}", None).unwrap(), "{}");
+		assert_eq!(
+			import_vars("${version_type}", Some("snapshot")).unwrap(),
+			"snapshot"
+		);
+		// the channel is only known if the patch has a type
+		assert!(import_vars("${version_type}", None).is_err());
+		assert!(import_vars("${unknown}", None).is_err());
+	}
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{fmt::Display, path::Path, sync::Mutex};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::write_json;
+
+#[derive(Serialize, Debug)]
+pub struct Failure {
+	pub source: &'static str,
+	pub item: String,
+	pub error: String,
+}
+
+/// Collects the failures of individual versions over a run.
+///
+/// Without `--keep-going` the first failure aborts the run as before; with it, failures are
+/// recorded and reported together by [Failures::finish].
+#[derive(Debug, Default)]
+pub struct Failures {
+	keep_going: bool,
+	failures: Mutex<Vec<Failure>>,
+}
+
+impl Failures {
+	pub fn new(keep_going: bool) -> Self {
+		Self {
+			keep_going,
+			failures: Mutex::default(),
+		}
+	}
+
+	/// Returns the value of `result`, or records its error and returns `None` when keeping going.
+	pub fn check<T>(
+		&self,
+		source: &'static str,
+		item: impl Display,
+		result: Result<T>,
+	) -> Result<Option<T>> {
+		match result {
+			Ok(value) => Ok(Some(value)),
+			Err(error) if self.keep_going => {
+				self.failures.lock().unwrap().push(Failure {
+					source,
+					item: item.to_string(),
+					error: format!("{error:#}"),
+				});
+				Ok(None)
+			}
+			Err(error) => Err(error.context(format!("Failed to process {source} {item}"))),
+		}
+	}
+
+	/// Prints every recorded failure, writes them to `errors_json` if given, and fails if there
+	/// were any.
+	pub fn finish(self, errors_json: Option<&Path>) -> Result<()> {
+		let failures = self.failures.into_inner().unwrap();
+		if let Some(path) = errors_json {
+			write_json(path, &failures)?;
+		}
+		if failures.is_empty() {
+			return Ok(());
+		}
+		eprintln!("{} failures:", failures.len());
+		for failure in &failures {
+			eprintln!("  {} {}: {}", failure.source, failure.item, failure.error);
+		}
+		bail!("{} items failed", failures.len())
+	}
+}
@@ -0,0 +1,174 @@
+/*
+ * Copyright 2023 kb1000
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a copy of the MPL was not distributed with this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, ensure, Result};
+use futures::{future, StreamExt};
+use reqwest::header::CONTENT_LENGTH;
+
+use helixlauncher_meta as helix;
+
+use crate::{config::Config, index, with_retry};
+
+/// Sends a HEAD request to every download, dev download, asset index and logging config URL in the
+/// output and reports the ones that fail, grouped by host. Transient failures are retried. Bodies
+/// are not downloaded and hashes are not checked.
+pub async fn validate_urls(client: &reqwest::Client, config: &Config) -> Result<()> {
+	let mut urls = BTreeSet::new();
+	for dir in index::component_dirs(&config.paths.out_root)? {
+		for component in index::load_components(&dir)? {
+			urls.extend(
+				component
+					.downloads
+					.into_iter()
+					.chain(component.dev_downloads)
+					.map(|download| download.url),
+			);
+			urls.extend(component.assets.map(|assets| assets.url));
+			urls.extend(component.logging.map(|logging| logging.url));
+		}
+	}
+	urls.retain(|url| is_remote(url));
+	let total = urls.len();
+
+	let broken: Vec<(String, String)> = futures::stream::iter(urls)
+		.map(|url| async move {
+			let result =
+				with_retry(|| async { Ok(client.head(&url).send().await?.error_for_status()?) })
+					.await;
+			match result {
+				Ok(_) => None,
+				Err(error) => {
+					let status = error
+						.downcast_ref::<reqwest::Error>()
+						.and_then(reqwest::Error::status);
+					let reason =
+						status.map_or_else(|| error.to_string(), |status| status.to_string());
+					Some((url, reason))
+				}
+			}
+		})
+		.buffer_unordered(config.concurrency)
+		.filter_map(future::ready)
+		.collect()
+		.await;
+
+	let broken = report_by_host(broken);
+	if broken > 0 {
+		bail!("{broken} of {total} URLs are broken");
+	}
+	println!("All {total} URLs are reachable");
+
+	Ok(())
+}
+
+/// Whether `url` is on a server, and so can be checked. Anything else, such as a `file:` URL, only
+/// works on the machine it was generated on.
+fn is_remote(url: &str) -> bool {
+	reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Prints `problems`, pairs of a URL and what is wrong with it, grouped by host, and returns how
+/// many there are.
+fn report_by_host(problems: Vec<(String, String)>) -> usize {
+	let mut by_host: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+	for (url, reason) in problems {
+		let host = reqwest::Url::parse(&url)
+			.ok()
+			.and_then(|url| url.host_str().map(str::to_owned))
+			.unwrap_or_else(|| String::from("<invalid URL>"));
+		by_host.entry(host).or_default().push((url, reason));
+	}
+
+	for (host, urls) in &mut by_host {
+		urls.sort();
+		println!("{host}: {} broken", urls.len());
+		for (url, reason) in urls {
+			println!("\t{url}: {reason}");
+		}
+	}
+
+	by_host.values().map(Vec::len).sum()
+}
+
+/// Checks that every download in the output is reachable and has the size recorded for it, as
+/// reported by a HEAD request. With `deep`, every download is fetched as well, to check its hash.
+pub async fn verify(client: &reqwest::Client, config: &Config, deep: bool) -> Result<()> {
+	// URL -> size and hash, deduplicated, as most libraries are shared between many versions
+	let mut downloads = BTreeMap::new();
+	for dir in index::component_dirs(&config.paths.out_root)? {
+		for component in index::load_components(&dir)? {
+			for download in component
+				.downloads
+				.into_iter()
+				.chain(component.dev_downloads)
+			{
+				if !is_remote(&download.url) {
+					continue;
+				}
+				downloads.insert(download.url, (download.size, download.hash));
+			}
+		}
+	}
+	let total = downloads.len();
+
+	let problems: Vec<(String, String)> = futures::stream::iter(downloads)
+		.map(|(url, (size, hash))| async move {
+			let problem = verify_download(client, &url, size, &hash, deep).await;
+			problem.err().map(|problem| (url, format!("{problem:#}")))
+		})
+		.buffer_unordered(config.concurrency)
+		.filter_map(future::ready)
+		.collect()
+		.await;
+
+	let broken = report_by_host(problems);
+	if broken > 0 {
+		bail!("{broken} of {total} downloads are broken");
+	}
+	println!("All {total} downloads match");
+
+	Ok(())
+}
+
+async fn verify_download(
+	client: &reqwest::Client,
+	url: &str,
+	size: u32,
+	hash: &helix::component::Hash,
+	deep: bool,
+) -> Result<()> {
+	let response = client.head(url).send().await?.error_for_status()?;
+	// not Response::content_length, which is the length of the (empty) body of the HEAD request
+	if let Some(length) = response
+		.headers()
+		.get(CONTENT_LENGTH)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.parse::<u64>().ok())
+	{
+		ensure!(
+			length == u64::from(size),
+			"size is {length}, expected {size}"
+		);
+	}
+	if deep {
+		let content = client
+			.get(url)
+			.send()
+			.await?
+			.error_for_status()?
+			.bytes()
+			.await?;
+		ensure!(
+			content.len() == size as usize,
+			"downloaded {} bytes, expected {size}",
+			content.len()
+		);
+		ensure!(hash.verify(&content), "content doesn't match the {hash}");
+	}
+	Ok(())
+}